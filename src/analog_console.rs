@@ -1,16 +1,60 @@
 use std::f32::consts::PI;
-use std::collections::VecDeque;
 
 use nih_plug::prelude::Enum;
 
+use crate::stereo::Biquad;
+
+/// Internal ceiling of the console (+24 dBFS). See `AnalogConsoleProcessor` for the gain structure.
+pub(crate) const INTERNAL_CEILING: f32 = 16.0;
+/// Tighter ceiling in Extreme mode (0 dBFS), where every type would otherwise sit at the
+/// internal ceiling
+const EXTREME_CEILING: f32 = 1.0;
+/// Extreme mode multiplies the drive range, 1-10 becomes 10-100
+pub(crate) const EXTREME_DRIVE_SCALE: f32 = 10.0;
+
 /// Analog-style console processor with saturation, EQ, crosstalk, and phase linearization
+///
+/// Gain structure: 1.0 is 0 dBFS at the console's input and output. The saturators are allowed
+/// to go over full scale (Cubic, Quintic and Bypass are unbounded with high Drive), so their
+/// output is softly bounded to `INTERNAL_CEILING` before crosstalk, the DC blocker and the
+/// linearizer see it. The bound is `INTERNAL_CEILING * tanh(x / INTERNAL_CEILING)`, which is
+/// within 0.02 dB of linear up to 0 dBFS, so normal levels pass through as before. Nothing after
+/// the saturation adds gain, so the console's output stays within the same ceiling.
+///
+/// Extreme mode scales the drive by `EXTREME_DRIVE_SCALE` for sound design and bounds the
+/// saturation to `EXTREME_CEILING` instead, so at up to 100x drive the output stays around full
+/// scale and the filters below see the same bounded signal as always.
 pub struct AnalogConsoleProcessor {
+    sample_rate: f32,
+
     // Saturation parameters
     drive: f32,
+    drive_input: f32,
+    drive_curve: DriveCurve,
+    extreme: bool,
     saturation_type: SaturationType,
 
+    // Band focused saturation: a low-pass split with the high band as its complement
+    saturation_band: SaturationBand,
+    band_freq_hz: f32,
+    band_split_left: Biquad,
+    band_split_right: Biquad,
+
+    // Antiderivative anti-aliasing for the polynomial types, with the previous saturator input
+    antialias: bool,
+    adaa_prev_left: f32,
+    adaa_prev_right: f32,
+
+    // Transient enhancement ahead of the saturation
+    transient_left: TransientEnhancer,
+    transient_right: TransientEnhancer,
+
     // Crosstalk parameters
     crosstalk_amount: f32,
+    crosstalk_model: CrosstalkModel,
+    // Shape the signal bleeding out of each channel into the other one
+    bleed_filter_left: BleedFilter,
+    bleed_filter_right: BleedFilter,
 
     // Internal state
     _prev_left: f32,
@@ -20,11 +64,76 @@ pub struct AnalogConsoleProcessor {
     _dc_blocker_left: DCBlocker,
     _dc_blocker_right: DCBlocker,
 
+    // DC coupled mode swaps the blocker for a bounded leak
+    input_coupling: InputCoupling,
+    dc_leak_left: DCLeak,
+    dc_leak_right: DCLeak,
+
     // Phase linearizer
     phase_linearizer_left: DCPhaseLinearizer,
     phase_linearizer_right: DCPhaseLinearizer,
 }
 
+/// Numeric precision used by the recursive (feedback) filters
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum Precision {
+    #[name = "32-bit"]
+    Single,
+    #[name = "64-bit"]
+    Double,
+}
+
+/// Whether the console strips DC (AC) or passes it through (DC)
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum InputCoupling {
+    #[name = "AC"]
+    Ac,
+    #[name = "DC"]
+    Dc,
+}
+
+/// How the Drive control maps onto saturation intensity
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum DriveCurve {
+    /// Drive is used as-is
+    Linear,
+    /// More travel in the gentle region, ramps up quickly at the top
+    Exponential,
+    /// Fine control at both ends, quick through the middle
+    #[name = "S-Curve"]
+    SCurve,
+}
+
+/// Below this input step the ADAA division is ill-conditioned and the midpoint is used instead
+const ADAA_EPSILON: f64 = 1.0e-5;
+
+/// How the channels couple into each other, which sets the color of the crosstalk bleed
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum CrosstalkModel {
+    /// Flat bleed at every frequency
+    Resistive,
+    /// Bleed rises 6 dB/octave up to `CAPACITIVE_CORNER_HZ`, mostly highs get through
+    Capacitive,
+    /// Bleed falls 6 dB/octave above `INDUCTIVE_CORNER_HZ`, mostly lows get through
+    Inductive,
+}
+
+/// Corner of the capacitive (high-pass) bleed
+const CAPACITIVE_CORNER_HZ: f32 = 2000.0;
+/// Corner of the inductive (low-pass) bleed
+const INDUCTIVE_CORNER_HZ: f32 = 300.0;
+
+/// Which part of the spectrum goes through the saturator. The other band is summed back clean.
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum SaturationBand {
+    #[name = "Full Band"]
+    Full,
+    /// Saturate above the split, for presence and grit with clean lows
+    Highs,
+    /// Saturate below the split, leaving the highs clean
+    Lows,
+}
+
 #[derive(Clone, Copy, PartialEq, Enum)]
 pub enum SaturationType {
     Tape,
@@ -37,38 +146,190 @@ pub enum SaturationType {
     Bypass,
 }
 
+impl SaturationType {
+    /// Short description of how the type sounds, for the editor
+    pub fn description(self) -> &'static str {
+        match self {
+            SaturationType::Tape => "Tape: warm and rounded, softens the highs and peaks",
+            SaturationType::Tube => "Tube: asymmetric, adds even harmonics",
+            SaturationType::Transistor => "Transistor: harder edge with a firmer knee",
+            SaturationType::LDR => "LDR: opens up the harder it's driven, gentle at low levels",
+            SaturationType::Cubic => "Cubic: odd harmonics that grow quickly with level, no ceiling",
+            SaturationType::Quintic => "Quintic: like Cubic with extra upper harmonics, no ceiling",
+            SaturationType::SoftClip => "Soft Clip: smooth limiting towards full scale",
+            SaturationType::Bypass => "Bypass: no saturation, Drive is plain gain",
+        }
+    }
+
+    /// Average (RMS) input level in dBFS where the type sounds its best at moderate drive, used
+    /// for the gain staging hint. The polynomial types run away quickly so they want less.
+    pub fn sweet_spot_db(self) -> f32 {
+        match self {
+            SaturationType::Tape => -12.0,
+            SaturationType::Tube => -14.0,
+            SaturationType::Transistor => -16.0,
+            SaturationType::LDR => -12.0,
+            SaturationType::Cubic => -18.0,
+            SaturationType::Quintic => -20.0,
+            SaturationType::SoftClip => -12.0,
+            SaturationType::Bypass => -18.0,
+        }
+    }
+}
+
 impl AnalogConsoleProcessor {
     pub fn new(sample_rate: f32) -> Self {
+        let band_freq_hz = 1000.0;
         Self {
+            sample_rate,
             drive: 0.5,
+            drive_input: 0.5,
+            drive_curve: DriveCurve::Linear,
+            extreme: false,
             saturation_type: SaturationType::Tape,
+            saturation_band: SaturationBand::Full,
+            band_freq_hz,
+            band_split_left: Biquad::lowpass(sample_rate, band_freq_hz),
+            band_split_right: Biquad::lowpass(sample_rate, band_freq_hz),
+            antialias: false,
+            adaa_prev_left: 0.0,
+            adaa_prev_right: 0.0,
+            transient_left: TransientEnhancer::new(sample_rate),
+            transient_right: TransientEnhancer::new(sample_rate),
             crosstalk_amount: 0.05,
+            crosstalk_model: CrosstalkModel::Resistive,
+            bleed_filter_left: BleedFilter::new(),
+            bleed_filter_right: BleedFilter::new(),
             _prev_left: 0.0,
             _prev_right: 0.0,
             _dc_blocker_left: DCBlocker::new(0.995),
             _dc_blocker_right: DCBlocker::new(0.995),
+            input_coupling: InputCoupling::Ac,
+            dc_leak_left: DCLeak::new(sample_rate),
+            dc_leak_right: DCLeak::new(sample_rate),
             phase_linearizer_left: DCPhaseLinearizer::new(sample_rate, 30.0),
             phase_linearizer_right: DCPhaseLinearizer::new(sample_rate, 30.0),
         }
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.band_split_left.set_lowpass(sample_rate, self.band_freq_hz);
+            self.band_split_right.set_lowpass(sample_rate, self.band_freq_hz);
+            self.update_bleed_filters();
+        }
+        self.transient_left.set_sample_rate(sample_rate);
+        self.transient_right.set_sample_rate(sample_rate);
+        self.dc_leak_left.set_sample_rate(sample_rate);
+        self.dc_leak_right.set_sample_rate(sample_rate);
         self.phase_linearizer_left.set_sample_rate(sample_rate);
         self.phase_linearizer_right.set_sample_rate(sample_rate);
     }
 
     pub fn set_drive(&mut self, drive: f32) {
-        self.drive = drive.clamp(1.0, 10.0);
+        self.drive_input = drive;
+        self.update_drive();
+    }
+
+    pub fn set_drive_curve(&mut self, curve: DriveCurve) {
+        self.drive_curve = curve;
+        self.update_drive();
+    }
+
+    /// Extreme mode: 10x drive range with a tighter output bound
+    pub fn set_extreme(&mut self, extreme: bool) {
+        if extreme != self.extreme {
+            self.extreme = extreme;
+            self.update_drive();
+        }
+    }
+
+    /// Remap the 1-10 drive range through the drive curve before it reaches `saturate`
+    fn update_drive(&mut self) {
+        let drive = self.drive_input.clamp(1.0, 10.0);
+        let position = (drive - 1.0) / 9.0;
+        let shaped = match self.drive_curve {
+            DriveCurve::Linear => position,
+            DriveCurve::Exponential => ((3.0 * position).exp() - 1.0) / (3.0_f32.exp() - 1.0),
+            DriveCurve::SCurve => position * position * (3.0 - 2.0 * position),
+        };
+        self.drive = 1.0 + 9.0 * shaped;
+        if self.extreme {
+            self.drive *= EXTREME_DRIVE_SCALE;
+        }
     }
 
     pub fn set_saturation_type(&mut self, sat_type: SaturationType) {
         self.saturation_type = sat_type;
     }
 
+    pub fn set_saturation_band(&mut self, band: SaturationBand) {
+        self.saturation_band = band;
+    }
+
+    /// Crossover between the saturated and clean bands
+    pub fn set_band_frequency(&mut self, freq_hz: f32) {
+        if freq_hz != self.band_freq_hz {
+            self.band_freq_hz = freq_hz;
+            self.band_split_left.set_lowpass(self.sample_rate, freq_hz);
+            self.band_split_right.set_lowpass(self.sample_rate, freq_hz);
+        }
+    }
+
+    /// Use the antiderivative (ADAA) form of the Cubic and Quintic shapers
+    pub fn set_antialiasing(&mut self, antialias: bool) {
+        self.antialias = antialias;
+    }
+
+    /// Switch the DC blockers and allpass filters between f32 and f64 processing
+    pub fn set_precision(&mut self, precision: Precision) {
+        self._dc_blocker_left.precision = precision;
+        self._dc_blocker_right.precision = precision;
+        self.phase_linearizer_left.allpass_filter.precision = precision;
+        self.phase_linearizer_right.allpass_filter.precision = precision;
+    }
+
+    pub fn set_input_coupling(&mut self, coupling: InputCoupling) {
+        self.input_coupling = coupling;
+    }
+
+    /// Transient boost into the saturation, 0-1
+    pub fn set_transient_enhance(&mut self, amount: f32) {
+        self.transient_left.set_amount(amount);
+        self.transient_right.set_amount(amount);
+    }
+
     pub fn set_crosstalk(&mut self, amount: f32) {
         self.crosstalk_amount = amount.clamp(0.0, 0.3);
     }
 
+    pub fn set_crosstalk_model(&mut self, model: CrosstalkModel) {
+        if model != self.crosstalk_model {
+            self.crosstalk_model = model;
+            self.update_bleed_filters();
+        }
+    }
+
+    fn update_bleed_filters(&mut self) {
+        let corner_hz = match self.crosstalk_model {
+            CrosstalkModel::Resistive => return,
+            CrosstalkModel::Capacitive => CAPACITIVE_CORNER_HZ,
+            CrosstalkModel::Inductive => INDUCTIVE_CORNER_HZ,
+        };
+        self.bleed_filter_left.set_frequency(self.sample_rate, corner_hz);
+        self.bleed_filter_right.set_frequency(self.sample_rate, corner_hz);
+    }
+
+    /// Filter the bleed coming out of one channel according to the crosstalk model
+    fn shape_bleed(model: CrosstalkModel, filter: &mut BleedFilter, sample: f32) -> f32 {
+        match model {
+            CrosstalkModel::Resistive => sample,
+            CrosstalkModel::Capacitive => sample - filter.process(sample),
+            CrosstalkModel::Inductive => filter.process(sample),
+        }
+    }
+
     pub fn set_phase_linearizer_freq(&mut self, freq_hz: f32) {
         if self.phase_linearizer_left.corner_freq != freq_hz {
             self.phase_linearizer_left.set_corner_frequency(freq_hz);
@@ -78,15 +339,34 @@ impl AnalogConsoleProcessor {
         }
     }
 
+    /// Samples of delay held in the internal delay lines, used as the plugin's tail length
+    pub fn tail_samples(&self) -> usize {
+        self.phase_linearizer_left
+            .tail_samples()
+            .max(self.phase_linearizer_right.tail_samples())
+    }
+
     /// Process a single stereo sample
     pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
-        // Apply soft saturation
-        let left_sat = self.saturate(left);
-        let right_sat = self.saturate(right);
-
-        // Apply crosstalk
-        let left_cross = (1.0 - self.crosstalk_amount) * left_sat + self.crosstalk_amount * right_sat;
-        let right_cross = (1.0 - self.crosstalk_amount) * right_sat + self.crosstalk_amount * left_sat;
+        // Sharpen the transients first so the drive bites into them
+        let left = self.transient_left.process(left);
+        let right = self.transient_right.process(right);
+
+        // Split off the lows even in full band so switching bands doesn't start from stale state
+        let left_low = self.band_split_left.process(left);
+        let right_low = self.band_split_right.process(right);
+
+        // Apply soft saturation, bounded to the internal headroom
+        let (left_sat, left_shaped) = self.saturate_band(left, left_low, self.adaa_prev_left);
+        let (right_sat, right_shaped) = self.saturate_band(right, right_low, self.adaa_prev_right);
+        self.adaa_prev_left = left_shaped;
+        self.adaa_prev_right = right_shaped;
+
+        // Apply crosstalk, colored by the coupling model
+        let left_bleed = Self::shape_bleed(self.crosstalk_model, &mut self.bleed_filter_left, left_sat);
+        let right_bleed = Self::shape_bleed(self.crosstalk_model, &mut self.bleed_filter_right, right_sat);
+        let left_cross = (1.0 - self.crosstalk_amount) * left_sat + self.crosstalk_amount * right_bleed;
+        let right_cross = (1.0 - self.crosstalk_amount) * right_sat + self.crosstalk_amount * left_bleed;
 
         // Subtle envelope following (transient smoothing)
         let left_smooth = 0.9 * left_cross + 0.1 * self._prev_left;
@@ -95,9 +375,18 @@ impl AnalogConsoleProcessor {
         self._prev_left = left_cross;
         self._prev_right = right_cross;
 
-        // Apply DC blocking to avoid unwanted offsets from the saturation
-        let left_dc_blocked = self._dc_blocker_left.process(left_smooth);
-        let right_dc_blocked = self._dc_blocker_right.process(right_smooth);
+        // Apply DC blocking to avoid unwanted offsets from the saturation. DC coupling keeps
+        // the offset and only leaks away what goes past the safety limit.
+        let (left_dc_blocked, right_dc_blocked) = match self.input_coupling {
+            InputCoupling::Ac => (
+                self._dc_blocker_left.process(left_smooth),
+                self._dc_blocker_right.process(right_smooth),
+            ),
+            InputCoupling::Dc => (
+                self.dc_leak_left.process(left_smooth),
+                self.dc_leak_right.process(right_smooth),
+            ),
+        };
 
         // Apply phase linearization
         let left_linearized = self.phase_linearizer_left.process(left_dc_blocked);
@@ -106,6 +395,67 @@ impl AnalogConsoleProcessor {
         (left_linearized, right_linearized)
     }
 
+    /// Saturate the selected band of `sample` and sum the other band back untouched. `low` is the
+    /// low-passed sample, the high band is what's left so the two always add back to `sample`.
+    /// Also returns what went into the saturator, the anti-aliasing needs it next sample.
+    fn saturate_band(&self, sample: f32, low: f32, previous: f32) -> (f32, f32) {
+        match self.saturation_band {
+            SaturationBand::Full => (self.bound_to_headroom(self.shape(sample, previous)), sample),
+            SaturationBand::Highs => {
+                let high = sample - low;
+                (low + self.bound_to_headroom(self.shape(high, previous)), high)
+            }
+            SaturationBand::Lows => (self.bound_to_headroom(self.shape(low, previous)) + (sample - low), low),
+        }
+    }
+
+    /// Run the saturator, through the anti-aliased form where there is one
+    fn shape(&self, sample: f32, previous: f32) -> f32 {
+        match self.saturation_type {
+            SaturationType::Cubic | SaturationType::Quintic if self.antialias => {
+                self.saturate_adaa(sample, previous)
+            }
+            _ => self.saturate(sample),
+        }
+    }
+
+    /// First order antiderivative anti-aliasing: the average of the shaper over the segment
+    /// between the last input and this one, `(F(x) - F(x1)) / (x - x1)`. This smooths the
+    /// harmonics that would fold back above Nyquist at the cost of half a sample of delay and a
+    /// gentle rolloff in the top octave.
+    fn saturate_adaa(&self, sample: f32, previous: f32) -> f32 {
+        let delta = sample as f64 - previous as f64;
+        if delta.abs() < ADAA_EPSILON {
+            // Too close to divide, the midpoint is the limit of the average
+            self.saturate(0.5 * (sample + previous))
+        } else {
+            ((self.antiderivative(sample as f64) - self.antiderivative(previous as f64)) / delta) as f32
+        }
+    }
+
+    /// Closed form antiderivatives of the polynomial shapers in `saturate`, in f64 since the
+    /// ADAA difference cancels most of the value
+    fn antiderivative(&self, x: f64) -> f64 {
+        let drive = self.drive as f64;
+        let x2 = x * x;
+        match self.saturation_type {
+            SaturationType::Cubic => x2 / 2.0 + drive * x2 * x2 / 4.0,
+            SaturationType::Quintic => {
+                let drive1 = 0.5 * drive;
+                let drive2 = 0.3 * drive;
+                x2 / 2.0 + drive1 * x2 * x2 / 4.0 + drive2 * x2 * x2 * x2 / 6.0
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Keep the saturator output inside `INTERNAL_CEILING` (or `EXTREME_CEILING`) so the filters
+    /// downstream only ever see a bounded signal
+    fn bound_to_headroom(&self, sample: f32) -> f32 {
+        let ceiling = if self.extreme { EXTREME_CEILING } else { INTERNAL_CEILING };
+        ceiling * (sample / ceiling).tanh()
+    }
+
     fn saturate(&self, sample: f32) -> f32 {
         let driven = sample * self.drive;
 
@@ -152,57 +502,190 @@ impl AnalogConsoleProcessor {
     }
 }
 
+/// Transient enhancer from a differential envelope: a fast and a slow envelope share the same
+/// release but differ in attack, so the fast one runs ahead of the slow one only at onsets.
+/// That difference is fed back as gain, boosting each hit by up to 6 dB at full amount.
+pub struct TransientEnhancer {
+    sample_rate: f32,
+    amount: f32,
+    fast_envelope: f32,
+    slow_envelope: f32,
+    fast_attack: f32,
+    slow_attack: f32,
+    release: f32,
+}
+
+impl TransientEnhancer {
+    const FAST_ATTACK_MS: f32 = 0.5;
+    const SLOW_ATTACK_MS: f32 = 15.0;
+    const RELEASE_MS: f32 = 60.0;
+    /// Keeps the ratio sane on near silence (about -80 dBFS)
+    const FLOOR: f32 = 0.0001;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut enhancer = Self {
+            sample_rate,
+            amount: 0.0,
+            fast_envelope: 0.0,
+            slow_envelope: 0.0,
+            fast_attack: 0.0,
+            slow_attack: 0.0,
+            release: 0.0,
+        };
+        enhancer.update_coefficients();
+        enhancer
+    }
+
+    fn coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+        (-1.0 / (time_ms * 0.001 * sample_rate)).exp()
+    }
+
+    fn update_coefficients(&mut self) {
+        self.fast_attack = Self::coefficient(Self::FAST_ATTACK_MS, self.sample_rate);
+        self.slow_attack = Self::coefficient(Self::SLOW_ATTACK_MS, self.sample_rate);
+        self.release = Self::coefficient(Self::RELEASE_MS, self.sample_rate);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
+        }
+    }
+
+    pub fn set_amount(&mut self, amount: f32) {
+        self.amount = amount.clamp(0.0, 1.0);
+    }
+
+    fn follow(envelope: f32, level: f32, attack: f32, release: f32) -> f32 {
+        let coeff = if level > envelope { attack } else { release };
+        level + (envelope - level) * coeff
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let level = input.abs();
+        self.fast_envelope = Self::follow(self.fast_envelope, level, self.fast_attack, self.release);
+        self.slow_envelope = Self::follow(self.slow_envelope, level, self.slow_attack, self.release);
+
+        if self.amount == 0.0 {
+            return input;
+        }
+
+        // How far the fast envelope is ahead, 0 when steady, capped at double
+        let difference = (self.fast_envelope / self.slow_envelope.max(Self::FLOOR) - 1.0).clamp(0.0, 1.0);
+        input * (1.0 + self.amount * difference)
+    }
+}
+
+/// First order low-pass for the crosstalk bleed. The capacitive model uses its complement as
+/// the matching first order high-pass.
+struct BleedFilter {
+    coeff: f32,
+    state: f32,
+}
+
+impl BleedFilter {
+    fn new() -> Self {
+        Self {
+            coeff: 0.0,
+            state: 0.0,
+        }
+    }
+
+    fn set_frequency(&mut self, sample_rate: f32, freq_hz: f32) {
+        self.coeff = (-2.0 * PI * freq_hz / sample_rate).exp();
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.state = input + (self.state - input) * self.coeff;
+        self.state
+    }
+}
+
 /// DC Phase Linearizer - Preserves phase relationship in low frequencies
+///
+/// The delay follows the corner frequency as a fractional number of samples, read from a ring
+/// buffer with linear interpolation. Changes glide over `DELAY_GLIDE_MS`, the allpass
+/// coefficient along with the delay, so automating Lin Hz doesn't step either one and click.
 pub struct DCPhaseLinearizer {
     sample_rate: f32,
     corner_freq: f32,
-    buffer: VecDeque<f32>,
+    buffer: Vec<f32>,
+    write_pos: usize,
     allpass_filter: AllpassFilter,
-    delay_samples: usize,
+    // Current (smoothed) and target delay in samples
+    delay_samples: f32,
+    target_delay_samples: f32,
+    glide_coeff: f32,
 }
 
+/// Time for a delay change to mostly settle (one-pole time constant)
+const DELAY_GLIDE_MS: f32 = 20.0;
+/// Lowest corner frequency, sets the longest delay the buffer has to hold
+const MIN_CORNER_FREQ: f32 = 20.0;
+const MAX_CORNER_FREQ: f32 = 800.0;
+
 impl DCPhaseLinearizer {
     pub fn new(sample_rate: f32, corner_freq_hz: f32) -> Self {
-        let allpass = AllpassFilter::new(sample_rate, corner_freq_hz);
-        let delay_samples = (sample_rate / corner_freq_hz * 0.25) as usize;
-
-        let mut buffer = VecDeque::with_capacity(delay_samples + 1);
-        buffer.resize(delay_samples, 0.0);
+        let glide_coeff = Self::calculate_glide_coeff(sample_rate);
+        let mut allpass = AllpassFilter::new(sample_rate, corner_freq_hz);
+        allpass.set_glide(glide_coeff);
+        let delay_samples = Self::calculate_delay(sample_rate, corner_freq_hz);
 
         Self {
             sample_rate,
             corner_freq: corner_freq_hz,
-            buffer,
+            buffer: vec![0.0; Self::buffer_len(sample_rate)],
+            write_pos: 0,
             allpass_filter: allpass,
             delay_samples,
+            target_delay_samples: delay_samples,
+            glide_coeff,
         }
     }
 
+    fn calculate_delay(sample_rate: f32, corner_freq: f32) -> f32 {
+        //sample_rate / corner_freq * 0.25
+        (sample_rate / (PI * corner_freq) - 1.0).max(0.0)
+    }
+
+    /// Room for the longest delay plus the extra sample the interpolation reads
+    fn buffer_len(sample_rate: f32) -> usize {
+        Self::calculate_delay(sample_rate, MIN_CORNER_FREQ).ceil() as usize + 2
+    }
+
+    fn calculate_glide_coeff(sample_rate: f32) -> f32 {
+        (-1.0 / (DELAY_GLIDE_MS * 0.001 * sample_rate)).exp()
+    }
+
+    /// Longest delay currently in use, in whole samples
+    pub fn tail_samples(&self) -> usize {
+        self.delay_samples.max(self.target_delay_samples).ceil() as usize
+    }
+
     pub fn set_sample_rate(&mut self, new_sample_rate: f32) {
+        // This gets called every block, so only touch the state on an actual change
+        if new_sample_rate == self.sample_rate {
+            return;
+        }
         self.sample_rate = new_sample_rate;
         self.allpass_filter.set_sample_rate(new_sample_rate);
-        // Recalculate delay based on the new sample rate and current corner frequency
-        //let new_delay = (self.sample_rate / self.corner_freq * 0.25) as usize;
-        let new_delay = (self.sample_rate / (PI * self.corner_freq) - 1.0).max(0.0) as usize;
-        if new_delay != self.delay_samples {
-            self.delay_samples = new_delay;
-            self.buffer.resize(self.delay_samples, 0.0);
-        }
+        self.glide_coeff = Self::calculate_glide_coeff(new_sample_rate);
+        self.allpass_filter.set_glide(self.glide_coeff);
+
+        // The old contents don't line up with the new rate, so start over from silence
+        self.buffer = vec![0.0; Self::buffer_len(new_sample_rate)];
+        self.write_pos = 0;
+        self.target_delay_samples = Self::calculate_delay(self.sample_rate, self.corner_freq);
+        self.delay_samples = self.target_delay_samples;
     }
 
     pub fn set_corner_frequency(&mut self, freq_hz: f32) {
-        self.corner_freq = freq_hz.clamp(20.0, 800.0);
+        self.corner_freq = freq_hz.clamp(MIN_CORNER_FREQ, MAX_CORNER_FREQ);
         self.allpass_filter.set_frequency(self.corner_freq);
 
-        // Recalculate delay when frequency changes
-        //let new_delay = (self.sample_rate / self.corner_freq * 0.25) as usize;
-        let new_delay = (self.sample_rate / (PI * self.corner_freq) - 1.0).max(0.0) as usize;
-
-        // Resize buffer if needed
-        if new_delay != self.delay_samples {
-            self.delay_samples = new_delay;
-            self.buffer.resize(self.delay_samples, 0.0);
-        }
+        // The delay glides towards this in process()
+        self.target_delay_samples = Self::calculate_delay(self.sample_rate, self.corner_freq);
     }
 
     pub fn process(&mut self, input: f32) -> f32 {
@@ -210,14 +693,19 @@ impl DCPhaseLinearizer {
         let allpass_out = self.allpass_filter.process(input);
 
         // Store in delay buffer
-        self.buffer.push_back(allpass_out);
+        let len = self.buffer.len();
+        self.buffer[self.write_pos] = allpass_out;
 
-        // Get delayed sample
-        let delayed = if let Some(sample) = self.buffer.pop_front() {
-            sample
-        } else {
-            0.0
-        };
+        // Glide the delay and read between the two samples around it
+        self.delay_samples = self.target_delay_samples
+            + (self.delay_samples - self.target_delay_samples) * self.glide_coeff;
+        let whole = self.delay_samples as usize;
+        let frac = self.delay_samples - whole as f32;
+        let newer = self.buffer[(self.write_pos + len - whole) % len];
+        let older = self.buffer[(self.write_pos + len - whole - 1) % len];
+        let delayed = newer + (older - newer) * frac;
+
+        self.write_pos = (self.write_pos + 1) % len;
 
         let crossover_coeff = 0.3;
         let low_mix_alt = input * crossover_coeff + delayed * (1.0 - crossover_coeff);
@@ -228,10 +716,20 @@ impl DCPhaseLinearizer {
 }
 
 /// Allpass filter for phase manipulation
+///
+/// State is stored as f64 so the filter can switch precision on the fly. In `Precision::Single`
+/// the math runs in f32 and the stored values stay exactly representable as f32.
+///
+/// Frequency changes can glide the coefficient instead of stepping it, since a step jumps the
+/// output by the coefficient change times the input.
 pub struct AllpassFilter {
-    a1: f32,
-    z1: f32,
+    a1: f64,
+    target_a1: f64,
+    glide_coeff: f64,
+    z1: f64,
     sample_rate: f32,
+    freq_hz: f32,
+    precision: Precision,
 }
 
 impl AllpassFilter {
@@ -240,64 +738,259 @@ impl AllpassFilter {
 
         Self {
             a1,
+            target_a1: a1,
+            glide_coeff: 0.0,
             z1: 0.0,
             sample_rate,
+            freq_hz,
+            precision: Precision::Single,
         }
     }
 
-    fn calculate_coefficient(freq_hz: f32, sample_rate: f32) -> f32 {
-        let t = (PI * freq_hz / sample_rate).tan();
+    fn calculate_coefficient(freq_hz: f32, sample_rate: f32) -> f64 {
+        let t = (std::f64::consts::PI * freq_hz as f64 / sample_rate as f64).tan();
         (t - 1.0) / (t + 1.0)
     }
 
     pub fn set_sample_rate(&mut self, new_sample_rate: f32) {
         self.sample_rate = new_sample_rate;
-        // Recalculate the coefficient based on the new sample rate and current frequency
-        self.a1 = Self::calculate_coefficient(self.get_frequency(), self.sample_rate);
+        // Recalculate the coefficient based on the new sample rate and current frequency.
+        // The frequency is stored rather than reverse calculated from the coefficient, since that
+        // drifted a little on every call and made the output depend on the host's block size.
+        self.a1 = Self::calculate_coefficient(self.freq_hz, self.sample_rate);
+        self.target_a1 = self.a1;
     }
 
-    pub fn set_frequency(&mut self, freq_hz: f32) {
-        self.a1 = Self::calculate_coefficient(freq_hz, self.sample_rate);
+    /// One-pole coefficient for gliding to a new frequency, 0 changes it instantly
+    pub fn set_glide(&mut self, glide_coeff: f32) {
+        self.glide_coeff = glide_coeff as f64;
     }
 
-    pub fn get_frequency(&self) -> f32 {
-        // This is a reverse calculation of the coefficient to get the frequency.
-        // It might not be perfectly accurate due to floating-point precision,
-        // but it should be good enough for recalculating the coefficient.
-        let t_minus_one = self.a1;
-        let t_plus_one = 1.0;
-        let t = (t_minus_one + t_plus_one) / (t_plus_one - t_minus_one);
-        (self.sample_rate * t.atan()) / PI
+    pub fn set_frequency(&mut self, freq_hz: f32) {
+        self.freq_hz = freq_hz;
+        self.target_a1 = Self::calculate_coefficient(freq_hz, self.sample_rate);
+        if self.glide_coeff <= 0.0 {
+            self.a1 = self.target_a1;
+        }
     }
 
     pub fn process(&mut self, input: f32) -> f32 {
+        self.a1 = self.target_a1 + (self.a1 - self.target_a1) * self.glide_coeff;
+
         // First-order allpass formula: y[n] = a1*x[n] + x[n-1] - a1*y[n-1]
-        let output = self.a1 * input + self.z1;
-        self.z1 = input - self.a1 * output;
-        output
+        match self.precision {
+            Precision::Single => {
+                let a1 = self.a1 as f32;
+                let output = a1 * input + self.z1 as f32;
+                self.z1 = (input - a1 * output) as f64;
+                output
+            }
+            Precision::Double => {
+                let input = input as f64;
+                let output = self.a1 * input + self.z1;
+                self.z1 = input - self.a1 * output;
+                output as f32
+            }
+        }
     }
 }
 
 /// DC blocker to remove DC offset introduced by asymmetric saturation
 pub struct DCBlocker {
-    r: f32,
-    x1: f32,
-    y1: f32,
+    r: f64,
+    x1: f64,
+    y1: f64,
+    precision: Precision,
 }
 
 impl DCBlocker {
     pub fn new(r: f32) -> Self {
         Self {
-            r: r.clamp(0.9, 0.999),
+            r: r.clamp(0.9, 0.999) as f64,
             x1: 0.0,
             y1: 0.0,
+            precision: Precision::Single,
         }
     }
 
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+    }
+
     pub fn process(&mut self, input: f32) -> f32 {
-        let output = input - self.x1 + self.r * self.y1;
-        self.x1 = input;
-        self.y1 = output;
-        output
+        match self.precision {
+            Precision::Single => {
+                let output = input - self.x1 as f32 + self.r as f32 * self.y1 as f32;
+                self.x1 = input as f64;
+                self.y1 = output as f64;
+                output
+            }
+            Precision::Double => {
+                let input = input as f64;
+                let output = input - self.x1 + self.r * self.y1;
+                self.x1 = input;
+                self.y1 = output;
+                output as f32
+            }
+        }
+    }
+}
+
+/// Bounded DC leak for DC coupled mode. Offsets within `limit` pass straight through, while a
+/// slow average of anything beyond it gets subtracted so asymmetric saturation can't run off.
+pub struct DCLeak {
+    sample_rate: f32,
+    coeff: f32,
+    average: f32,
+    limit: f32,
+}
+
+impl DCLeak {
+    /// Time constant of the offset tracker
+    const TIME_SECONDS: f32 = 1.0;
+
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            coeff: Self::calculate_coefficient(sample_rate),
+            average: 0.0,
+            limit: 0.5,
+        }
+    }
+
+    fn calculate_coefficient(sample_rate: f32) -> f32 {
+        (-1.0 / (Self::TIME_SECONDS * sample_rate)).exp()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.coeff = Self::calculate_coefficient(sample_rate);
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.average = self.coeff * self.average + (1.0 - self.coeff) * input;
+        let excess = self.average - self.average.clamp(-self.limit, self.limit);
+        input - excess
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Average output of a DC offset plus a tone after a minute through the blocker, measured
+    /// over the last second. Ideally exactly 0, anything left is numerical error.
+    fn dc_residual(precision: Precision) -> f64 {
+        let sample_rate: f64 = 48000.0;
+        let seconds = 60;
+        let mut blocker = DCBlocker::new(0.995);
+        blocker.precision = precision;
+
+        let total = seconds * sample_rate as usize;
+        let measure_from = total - sample_rate as usize;
+        let mut sum = 0.0;
+        for n in 0..total {
+            // 1 kHz repeats every 48 samples, so the ideal output averages to exactly 0
+            let tone = 0.25 * (std::f64::consts::TAU * 1000.0 * n as f64 / sample_rate).sin();
+            let output = blocker.process((0.5 + tone) as f32);
+            if n >= measure_from {
+                sum += output as f64;
+            }
+        }
+        (sum / sample_rate).abs()
+    }
+
+    /// Largest sample to sample step of a 50 Hz sine through the linearizer, optionally jumping
+    /// the corner frequency between its extremes every 50 ms
+    fn largest_step(sweep: bool) -> f32 {
+        let sample_rate = 48000.0;
+        let mut linearizer = DCPhaseLinearizer::new(sample_rate, 150.0);
+        let mut previous = 0.0;
+        let mut largest: f32 = 0.0;
+        for n in 0..sample_rate as usize {
+            if sweep && n % 2400 == 0 {
+                let corner = if (n / 2400) % 2 == 0 { MIN_CORNER_FREQ } else { MAX_CORNER_FREQ };
+                linearizer.set_corner_frequency(corner);
+            }
+            let input = 0.5 * (2.0 * PI * 50.0 * n as f32 / sample_rate).sin();
+            let output = linearizer.process(input);
+            // Skip the start up from silence
+            if n > 4800 {
+                largest = largest.max((output - previous).abs());
+            }
+            previous = output;
+        }
+        largest
+    }
+
+    #[test]
+    fn linearizer_corner_changes_dont_click() {
+        let steady = largest_step(false);
+        let swept = largest_step(true);
+        // Jumping the delay by hundreds of samples or stepping the allpass would show up as a
+        // step many times larger than the sine's own
+        assert!(
+            swept < steady * 1.5,
+            "largest step {swept} with corner changes vs {steady} without"
+        );
+    }
+
+    /// Magnitude of one DFT bin, `frequency` has to fit a whole number of cycles in `signal`
+    fn bin_magnitude(signal: &[f32], frequency: f64, sample_rate: f64) -> f64 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (n, &sample) in signal.iter().enumerate() {
+            let phase = std::f64::consts::TAU * frequency * n as f64 / sample_rate;
+            re += sample as f64 * phase.cos();
+            im -= sample as f64 * phase.sin();
+        }
+        (re * re + im * im).sqrt()
+    }
+
+    /// Level of the 3 kHz alias relative to the fundamental when a 15 kHz sine is saturated at
+    /// 48 kHz. The 3rd harmonic at 45 kHz folds back to 3 kHz.
+    fn relative_alias_db(sat_type: SaturationType, antialias: bool) -> f64 {
+        let sample_rate = 48000.0;
+        let mut console = AnalogConsoleProcessor::new(sample_rate);
+        console.set_saturation_type(sat_type);
+        console.set_drive(4.0);
+        console.set_antialiasing(antialias);
+
+        let output: Vec<f32> = (0..2 * sample_rate as usize)
+            .map(|n| {
+                let input = 0.5 * (2.0 * PI * 15000.0 * n as f32 / sample_rate).sin();
+                console.process(input, input).0
+            })
+            .collect();
+        // Measure the second half, after the filters have settled
+        let settled = &output[sample_rate as usize..];
+        let fundamental = bin_magnitude(settled, 15000.0, sample_rate as f64);
+        let alias = bin_magnitude(settled, 3000.0, sample_rate as f64);
+        20.0 * (alias / fundamental).log10()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn adaa_reduces_aliasing() {
+        for sat_type in [SaturationType::Cubic, SaturationType::Quintic] {
+            let naive = relative_alias_db(sat_type, false);
+            let adaa = relative_alias_db(sat_type, true);
+            // First order ADAA is good for about 6-7 dB here, leave some margin
+            assert!(
+                adaa < naive - 3.0,
+                "alias at {adaa:.1} dB with ADAA vs {naive:.1} dB without"
+            );
+        }
+    }
+
+    #[test]
+    fn double_precision_dc_blocker_drifts_less() {
+        let single = dc_residual(Precision::Single);
+        let double = dc_residual(Precision::Double);
+        // The f32 rounding error builds up in the feedback, f64 stays far below it
+        assert!(
+            double * 10.0 < single,
+            "f64 residual {double:e} should be well below f32 {single:e}"
+        );
+    }
+}
@@ -1,26 +1,142 @@
 
+use nih_plug::prelude::Enum;
+
+use crate::analog_console::Precision;
+use crate::stereo::Biquad;
+
+/// Upward boost is capped so quiet passages don't get pulled up endlessly
+const MAX_UPWARD_BOOST_DB: f32 = 12.0;
+/// Below this envelope level (about -60 dBFS) upward mode leaves the signal alone to avoid
+/// amplifying the noise floor
+const UPWARD_FLOOR: f32 = 0.001;
+
+/// Where the compressor's makeup gain comes from
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum MakeupMode {
+    /// The original fixed 1.4x makeup
+    Fixed,
+    /// Tracks the average gain reduction so the compressor stays loudness neutral
+    Auto,
+    /// No internal makeup, use the Gain control instead
+    Manual,
+}
+
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum CompDirection {
+    /// Reduce gain above the threshold
+    Downward,
+    /// Raise gain below the threshold, leaving louder signal untouched
+    Upward,
+}
+
+/// Attack/release time, either in milliseconds (follows the sample rate) or pinned to a number of
+/// samples. A time of `ms` milliseconds is `ms * 0.001 * sample_rate` samples, both give the
+/// same one-pole coefficient `exp(-1 / samples)` at that sample rate.
+#[derive(Clone, Copy, PartialEq)]
+enum TimeConstant {
+    Milliseconds(f32),
+    Samples(f32),
+}
+
+impl TimeConstant {
+    fn coefficient(self, sample_rate: f32) -> f64 {
+        let samples = match self {
+            TimeConstant::Milliseconds(ms) => ms as f64 * 0.001 * sample_rate as f64,
+            TimeConstant::Samples(samples) => samples as f64,
+        };
+        (-1.0 / samples.max(1.0)).exp()
+    }
+}
+
+/// High-pass on the detector key so the lows don't drive the gain reduction. A frequency of 0
+/// turns it off.
+pub struct DetectorHighpass {
+    sample_rate: f32,
+    freq_hz: f32,
+    left: Biquad,
+    right: Biquad,
+}
+
+impl DetectorHighpass {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            freq_hz: 0.0,
+            left: Biquad::highpass(sample_rate, 20.0),
+            right: Biquad::highpass(sample_rate, 20.0),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_filters();
+        }
+    }
+
+    pub fn set_frequency(&mut self, freq_hz: f32) {
+        if freq_hz != self.freq_hz {
+            self.freq_hz = freq_hz;
+            self.update_filters();
+        }
+    }
+
+    fn update_filters(&mut self) {
+        if self.freq_hz > 0.0 {
+            self.left.set_highpass(self.sample_rate, self.freq_hz);
+            self.right.set_highpass(self.sample_rate, self.freq_hz);
+        }
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if self.freq_hz > 0.0 {
+            (self.left.process(left), self.right.process(right))
+        } else {
+            (left, right)
+        }
+    }
+}
+
 /// A simplified automatic compressor with dynamic ratio system
 pub struct SimpleAutoCompressor {
     sample_rate: f32,
-    envelope: f32,
-    gain_reduction: f32,
+    envelope: f64,
+    gain_reduction: f64,
     
-    attack_coeff: f32,
-    release_coeff: f32,
+    attack: TimeConstant,
+    release: TimeConstant,
+    attack_coeff: f64,
+    release_coeff: f64,
     
     // Level tracking
-    peak_average: f32,
+    peak_average: f64,
+
+    // Precision of the envelope/level recursion
+    precision: Precision,
+
+    direction: CompDirection,
+
+    // Run the detector and meters but leave the audio alone
+    analyze_only: bool,
+
+    // Auto makeup tracking
+    makeup_mode: MakeupMode,
+    makeup_lag_ms: f32,
+    makeup_coeff: f64,
+    average_gain_reduction_db: f64,
     
     // Meters
     pub input_level: f32,
     pub output_level: f32,
     pub gain_reduction_db: f32,
+    // Current auto-threshold as linear gain
+    pub threshold: f32,
 }
 
 impl SimpleAutoCompressor {
     pub fn new(sample_rate: f32) -> Self {
-        let attack_ms = 15.0;
-        let release_ms = 200.0;
+        let attack = TimeConstant::Milliseconds(15.0);
+        let release = TimeConstant::Milliseconds(200.0);
         
         Self {
             sample_rate,
@@ -28,68 +144,203 @@ impl SimpleAutoCompressor {
             gain_reduction: 1.0,
             
             // Pre-calculate coefficients
-            attack_coeff: (-1.0 / (attack_ms * 0.001 * sample_rate)).exp(),
-            release_coeff: (-1.0 / (release_ms * 0.001 * sample_rate)).exp(),
+            attack,
+            release,
+            attack_coeff: attack.coefficient(sample_rate),
+            release_coeff: release.coefficient(sample_rate),
             
             peak_average: 0.0,
+            precision: Precision::Single,
+            direction: CompDirection::Downward,
+            analyze_only: false,
+
+            makeup_mode: MakeupMode::Fixed,
+            makeup_lag_ms: 1000.0,
+            makeup_coeff: Self::calculate_lag_coefficient(1000.0, sample_rate),
+            average_gain_reduction_db: 0.0,
             
             input_level: 0.0,
             output_level: 0.0,
             gain_reduction_db: 0.0,
+            threshold: 0.0,
         }
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
         self.sample_rate = sample_rate;
-        self.attack_coeff = (-1.0 / (15.0 * 0.001 * sample_rate)).exp();
-        self.release_coeff = (-1.0 / (200.0 * 0.001 * sample_rate)).exp();
+        // Times set in samples come out the same at any sample rate
+        self.attack_coeff = self.attack.coefficient(sample_rate);
+        self.release_coeff = self.release.coefficient(sample_rate);
+        self.makeup_coeff = Self::calculate_lag_coefficient(self.makeup_lag_ms, sample_rate);
+    }
+
+    fn calculate_lag_coefficient(lag_ms: f32, sample_rate: f32) -> f64 {
+        (-1.0 / (lag_ms as f64 * 0.001 * sample_rate as f64)).exp()
+    }
+
+    pub fn set_makeup_mode(&mut self, mode: MakeupMode) {
+        self.makeup_mode = mode;
+    }
+
+    /// How quickly the auto makeup follows the average gain reduction
+    pub fn set_makeup_lag(&mut self, lag_ms: f32) {
+        if lag_ms != self.makeup_lag_ms {
+            self.makeup_lag_ms = lag_ms;
+            self.makeup_coeff = Self::calculate_lag_coefficient(lag_ms, self.sample_rate);
+        }
+    }
+
+    /// Set the attack time in milliseconds, scaled by the current sample rate
+    pub fn set_attack_ms(&mut self, attack_ms: f32) {
+        self.attack = TimeConstant::Milliseconds(attack_ms);
+        self.attack_coeff = self.attack.coefficient(self.sample_rate);
+    }
+
+    /// Set the release time in milliseconds, scaled by the current sample rate
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        self.release = TimeConstant::Milliseconds(release_ms);
+        self.release_coeff = self.release.coefficient(self.sample_rate);
+    }
+
+    /// Set the attack time directly in samples, bypassing the ms conversion so the envelope is
+    /// identical at every sample rate. Equivalent to `set_attack_ms(samples / sample_rate * 1000)`
+    /// at the current rate, but stays pinned to `samples` when the rate changes.
+    pub fn set_attack_samples(&mut self, samples: f32) {
+        self.attack = TimeConstant::Samples(samples);
+        self.attack_coeff = self.attack.coefficient(self.sample_rate);
+    }
+
+    /// Set the release time directly in samples, see `set_attack_samples`
+    pub fn set_release_samples(&mut self, samples: f32) {
+        self.release = TimeConstant::Samples(samples);
+        self.release_coeff = self.release.coefficient(self.sample_rate);
+    }
+
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+    }
+
+    pub fn set_direction(&mut self, direction: CompDirection) {
+        self.direction = direction;
+    }
+
+    /// Analyze only: everything gets computed, including `gain_reduction_db`, but `process`
+    /// returns the input untouched
+    pub fn set_analyze_only(&mut self, analyze_only: bool) {
+        self.analyze_only = analyze_only;
+    }
+
+    /// One-pole smoothing step `target * (1 - coeff) + current * coeff` at the current precision
+    fn smooth(&self, current: f64, target: f64, coeff: f64) -> f64 {
+        match self.precision {
+            Precision::Single => {
+                let coeff = coeff as f32;
+                (target as f32 * (1.0 - coeff) + current as f32 * coeff) as f64
+            }
+            Precision::Double => target * (1.0 - coeff) + current * coeff,
+        }
     }
     
     /// Process a single sample of audio
     pub fn process(&mut self, input: f32) -> f32 {
+        self.process_keyed(input, input)
+    }
+
+    /// Process a single sample of audio with the detector listening to `key` instead, e.g. an
+    /// external sidechain. The gain is applied to `input`.
+    pub fn process_keyed(&mut self, input: f32, key: f32) -> f32 {
         // Update input level
-        self.input_level = 0.9 * self.input_level + 0.1 * input.abs();
+        self.input_level = 0.9 * self.input_level + 0.1 * key.abs();
         
-        let input_abs = input.abs();
+        let input_abs = key.abs() as f64;
         
         if input_abs > self.envelope {
-            self.envelope = input_abs * (1.0 - self.attack_coeff) + self.envelope * self.attack_coeff;
+            self.envelope = self.smooth(self.envelope, input_abs, self.attack_coeff);
         } else {
-            self.envelope = input_abs * (1.0 - self.release_coeff) + self.envelope * self.release_coeff;
+            self.envelope = self.smooth(self.envelope, input_abs, self.release_coeff);
         }
         
         // Update peak memory with simple averaging
-        self.peak_average = 0.995 * self.peak_average + 0.005 * self.envelope;
+        self.peak_average = self.smooth(self.peak_average, self.envelope, 0.995);
         
         // Simple auto-threshold based on recent peak average
-        let threshold = self.peak_average * 0.5;
+        let threshold = self.peak_average as f32 * 0.5;
+        let envelope = self.envelope as f32;
+        self.threshold = threshold;
         
-        // Calculate gain reduction with dynamic ratio
-        if self.envelope <= threshold {
-            self.gain_reduction = 1.0;
-        } else {
-            // Calculate how far above threshold we are (in dB)
-            let excess_db = 20.0 * (self.envelope / threshold).log10();
-            let ratio = self.calculate_dynamic_ratio(excess_db);
-            
-            let reduction_db = excess_db - (excess_db / ratio);
-            let target_gain = 10.0_f32.powf(-reduction_db / 20.0);
-            
-            self.gain_reduction = 0.9 * self.gain_reduction + 0.1 * target_gain;
+        match self.direction {
+            CompDirection::Downward => {
+                // Calculate gain reduction with dynamic ratio
+                if envelope <= threshold {
+                    self.gain_reduction = 1.0;
+                } else {
+                    // Calculate how far above threshold we are (in dB)
+                    let excess_db = 20.0 * (envelope / threshold).log10();
+                    let ratio = self.calculate_dynamic_ratio(excess_db);
+                    
+                    let reduction_db = excess_db - (excess_db / ratio);
+                    let target_gain = 10.0_f32.powf(-reduction_db / 20.0);
+                    
+                    self.gain_reduction = self.smooth(self.gain_reduction, target_gain as f64, 0.9);
+                }
+            }
+            CompDirection::Upward => {
+                // Boost quiet signal towards the threshold with the same dynamic ratio
+                let target_gain = if envelope >= threshold || envelope < UPWARD_FLOOR {
+                    1.0
+                } else {
+                    // Calculate how far below threshold we are (in dB)
+                    let deficit_db = 20.0 * (threshold / envelope).log10();
+                    let ratio = self.calculate_dynamic_ratio(deficit_db);
+
+                    let boost_db = (deficit_db - (deficit_db / ratio)).min(MAX_UPWARD_BOOST_DB);
+                    10.0_f32.powf(boost_db / 20.0)
+                };
+
+                self.gain_reduction = self.smooth(self.gain_reduction, target_gain as f64, 0.9);
+            }
         }
         
         // Apply compression
-        let output = input * self.gain_reduction;
+        let gain_reduction = self.gain_reduction as f32;
+        let output = input * gain_reduction;
         
-        // Simple makeup gain
-        let makeup_gain = 1.4;
+        // Update meters
+        self.gain_reduction_db = -20.0 * gain_reduction.log10();
+
+        // Slow average of the gain reduction for the auto makeup
+        self.average_gain_reduction_db = self.smooth(
+            self.average_gain_reduction_db,
+            self.gain_reduction_db as f64,
+            self.makeup_coeff,
+        );
+
+        let makeup_gain = match self.makeup_mode {
+            // Simple makeup gain, upward mode leaves the loud parts at unity instead
+            MakeupMode::Fixed => match self.direction {
+                CompDirection::Downward => 1.4,
+                CompDirection::Upward => 1.0,
+            },
+            // Add back what's being taken away on average. Upward stays at unity, taking the
+            // average boost back out would undo the upward compression.
+            MakeupMode::Auto => match self.direction {
+                CompDirection::Downward => 10.0_f32.powf(self.average_gain_reduction_db as f32 / 20.0),
+                CompDirection::Upward => 1.0,
+            },
+            MakeupMode::Manual => 1.0,
+        };
         let output_with_makeup = output * makeup_gain;
         
-        // Update meters
-        self.gain_reduction_db = -20.0 * self.gain_reduction.log10();
         self.output_level = 0.9 * self.output_level + 0.1 * output_with_makeup.abs();
         
-        output_with_makeup
+        if self.analyze_only {
+            input
+        } else {
+            output_with_makeup
+        }
     }
     
     /// Calculate dynamic ratio based on how far above threshold the signal is
@@ -0,0 +1,101 @@
+// clipper.rs - Ardura 2025
+// Output peak clipper that only touches the top of the waveform
+
+use nih_plug::util;
+
+/// Just under full scale so a 0 dB threshold never actually hits 0 dBFS
+const FULL_SCALE_CEILING: f32 = 0.9999;
+
+/// Peak clipper with an adjustable threshold and soft knee.
+/// A 0 dB threshold with no knee is a plain clamp at `FULL_SCALE_CEILING`.
+pub struct PeakClipper {
+    ceiling: f32,
+    knee: f32,
+    // Release of the linked gain envelope, 0 ms is the plain per-sample curve
+    release_ms: f32,
+    release_coeff: f32,
+    envelope_gain: f32,
+}
+
+impl Default for PeakClipper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PeakClipper {
+    pub fn new() -> Self {
+        Self {
+            ceiling: FULL_SCALE_CEILING,
+            knee: 0.0,
+            release_ms: 0.0,
+            release_coeff: 0.0,
+            envelope_gain: 1.0,
+        }
+    }
+
+    /// Set the clip threshold in dB relative to full scale
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.ceiling = FULL_SCALE_CEILING * util::db_to_gain(threshold_db.min(0.0));
+    }
+
+    /// Knee width as a fraction of the ceiling, 0.0 is a hard knee
+    pub fn set_knee(&mut self, knee: f32) {
+        self.knee = knee.clamp(0.0, 1.0);
+    }
+
+    /// Release time of `process_stereo` in milliseconds
+    pub fn set_release(&mut self, sample_rate: f32, release_ms: f32) {
+        self.release_ms = release_ms.max(0.0);
+        self.release_coeff = if self.release_ms > 0.0 {
+            (-1.0 / (self.release_ms * 0.001 * sample_rate)).exp()
+        } else {
+            0.0
+        };
+    }
+
+    /// Stereo linked limiting with release. The gain drops instantly to whatever the clip curve
+    /// needs on either channel, then recovers over the release time instead of snapping back,
+    /// so peaks get turned down rather than squared off. The gain never sits above the static
+    /// curve, so the ceiling holds exactly as in `process`. With no release this is `process`
+    /// on each channel.
+    pub fn process_stereo(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if self.release_ms <= 0.0 {
+            self.envelope_gain = 1.0;
+            return (self.process(left), self.process(right));
+        }
+
+        let target = self.static_gain(left).min(self.static_gain(right));
+        self.envelope_gain = if target < self.envelope_gain {
+            target
+        } else {
+            target + (self.envelope_gain - target) * self.release_coeff
+        };
+        (left * self.envelope_gain, right * self.envelope_gain)
+    }
+
+    /// Gain the clip curve applies to this sample
+    fn static_gain(&self, sample: f32) -> f32 {
+        if sample.abs() <= self.ceiling * (1.0 - self.knee) {
+            1.0
+        } else {
+            self.process(sample) / sample
+        }
+    }
+
+    pub fn process(&self, sample: f32) -> f32 {
+        let knee_start = self.ceiling * (1.0 - self.knee);
+        let magnitude = sample.abs();
+
+        if magnitude <= knee_start {
+            // Below the knee is untouched
+            sample
+        } else if self.knee <= 0.0 {
+            sample.signum() * self.ceiling
+        } else {
+            // Ease into the ceiling across the knee
+            let knee_range = self.ceiling - knee_start;
+            sample.signum() * (knee_start + knee_range * ((magnitude - knee_start) / knee_range).tanh())
+        }
+    }
+}
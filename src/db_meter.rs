@@ -1,8 +1,73 @@
 // db_meter.rs - Ardura 2023
 // A decibel meter akin to Vizia's nice one in nih-plug
 
+use nih_plug::prelude::Enum;
 use nih_plug_egui::egui::{lerp, vec2, Color32, NumExt, Pos2, Rect, Response, Sense, Shape, Stroke, StrokeKind, TextStyle, TextWrapMode, Ui, Vec2, Widget, WidgetText};
 
+/// How often the meter values get computed in the audio thread
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum MeterMode {
+    /// Once per block from the block's peak, cheaper with many instances
+    #[name = "Per Block"]
+    PerBlock,
+    /// Every sample
+    #[name = "Per Sample"]
+    PerSample,
+}
+
+/// Length of the crest factor window
+const CREST_WINDOW_MS: f32 = 300.0;
+
+/// Collects peak and RMS over a fixed window for a crest factor (peak to RMS ratio) readout
+pub struct CrestMeter {
+    window_samples: usize,
+    count: usize,
+    peak: f32,
+    sum_squares: f64,
+}
+
+impl CrestMeter {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            window_samples: Self::calculate_window(sample_rate),
+            count: 0,
+            peak: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    fn calculate_window(sample_rate: f32) -> usize {
+        ((CREST_WINDOW_MS * 0.001 * sample_rate) as usize).max(1)
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.window_samples = Self::calculate_window(sample_rate);
+    }
+
+    /// Add a stereo sample. Returns the crest factor in dB whenever a window completes, silent
+    /// windows have no crest factor and return `None`
+    pub fn process(&mut self, left: f32, right: f32) -> Option<f32> {
+        self.peak = self.peak.max(left.abs()).max(right.abs());
+        self.sum_squares += (left * left + right * right) as f64;
+        self.count += 1;
+        if self.count < self.window_samples {
+            return None;
+        }
+
+        let rms = (self.sum_squares / (2 * self.count) as f64).sqrt() as f32;
+        let peak = self.peak;
+        self.count = 0;
+        self.peak = 0.0;
+        self.sum_squares = 0.0;
+
+        if rms > 0.0 {
+            Some(20.0 * (peak / rms).log10())
+        } else {
+            None
+        }
+    }
+}
+
 // TODO - let percentage work?
 #[allow(dead_code)]
 enum DBMeterText {
@@ -15,6 +80,7 @@ pub struct DBMeter {
     level: f32,
     desired_width: Option<f32>,
     text: Option<DBMeterText>,
+    marker: Option<f32>,
     animate: bool,
     border_color: Color32,
     bar_color: Color32,
@@ -29,6 +95,7 @@ impl DBMeter {
             level: level.clamp(0.0, 1.0),
             desired_width: None,
             text: None,
+            marker: None,
             animate: false,
             border_color: Color32::BLACK,
             bar_color: Color32::GREEN,
@@ -48,6 +115,12 @@ impl DBMeter {
         self
     }
 
+    /// Draw a marker tick at a `[0, 1]` position, e.g. a threshold
+    pub fn marker(mut self, position: f32) -> Self {
+        self.marker = Some(position.clamp(0.0, 1.0));
+        self
+    }
+
     /// Set the color of the outline and text
     pub fn set_border_color(&mut self, new_color: Color32) {
         self.border_color = new_color;
@@ -71,6 +144,7 @@ impl Widget for DBMeter {
             level,
             desired_width,
             text,
+            marker,
             animate, 
             border_color, 
             bar_color, 
@@ -147,6 +221,13 @@ impl Widget for DBMeter {
                 ui.painter().add(Shape::line(points,Stroke::new(1.0, self.border_color),));
             }
 
+            // Marker line drawn across the full meter height
+            if let Some(position) = marker {
+                let x = outer_rect.left() + outer_rect.width() * position;
+                let points: Vec<Pos2> = vec![Pos2::new(x, outer_rect.top()), Pos2::new(x, outer_rect.bottom())];
+                ui.painter().add(Shape::line(points, Stroke::new(2.0, Color32::YELLOW)));
+            }
+
             if let Some(text_kind) = text {
                 let text = match text_kind {
                     DBMeterText::Custom(text) => text,
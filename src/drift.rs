@@ -0,0 +1,116 @@
+// drift.rs - Ardura 2025
+// Slow random wandering of console parameters for a less static, analog feel
+
+use crate::rng::XorShiftRng;
+
+/// Drive wanders by up to this fraction either way at full Life
+const DRIVE_DEPTH: f32 = 0.1;
+/// Crosstalk wanders by up to this fraction either way at full Life
+const CROSSTALK_DEPTH: f32 = 0.5;
+/// Linearizer frequency wanders by up to this fraction either way at full Life
+const LINEARIZER_DEPTH: f32 = 0.1;
+/// The LFOs move in steps of this many samples. The count carries over between blocks, so where
+/// the host splits its blocks doesn't change the modulation.
+const STEP_SAMPLES: usize = 32;
+
+/// Smooth random LFO: glides from one random point in `[-1, 1)` to the next, picking a new one
+/// every `1 / rate_hz` seconds. Random values come from the shared seeded generator so the
+/// wandering recalls identically per session.
+struct DriftLfo {
+    rate_hz: f32,
+    phase: f32,
+    start: f32,
+    target: f32,
+}
+
+impl DriftLfo {
+    fn new(rate_hz: f32) -> Self {
+        Self {
+            rate_hz,
+            phase: 0.0,
+            start: 0.0,
+            target: 0.0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.phase = 0.0;
+        self.start = 0.0;
+        self.target = 0.0;
+    }
+
+    fn advance(&mut self, rng: &mut XorShiftRng, samples: usize, sample_rate: f32) {
+        self.phase += samples as f32 * self.rate_hz / sample_rate;
+        while self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.start = self.target;
+            self.target = rng.next_bipolar();
+        }
+    }
+
+    /// Current position, eased between the two points so the wandering has no corners
+    fn value(&self) -> f32 {
+        let eased = self.phase * self.phase * (3.0 - 2.0 * self.phase);
+        self.start + (self.target - self.start) * eased
+    }
+}
+
+/// Independent slow LFOs for drive, crosstalk and linearizer frequency. The rates are unrelated
+/// so the three never line up into an obvious cycle. At a Life of 0 every scale is exactly 1.
+pub struct ConsoleDrift {
+    drive: DriftLfo,
+    crosstalk: DriftLfo,
+    linearizer: DriftLfo,
+    samples_until_step: usize,
+}
+
+impl Default for ConsoleDrift {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsoleDrift {
+    pub fn new() -> Self {
+        Self {
+            drive: DriftLfo::new(0.13),
+            crosstalk: DriftLfo::new(0.21),
+            linearizer: DriftLfo::new(0.07),
+            samples_until_step: STEP_SAMPLES,
+        }
+    }
+
+    /// Start over from the center, used alongside reseeding the generator
+    pub fn reset(&mut self) {
+        self.drive.reset();
+        self.crosstalk.reset();
+        self.linearizer.reset();
+        self.samples_until_step = STEP_SAMPLES;
+    }
+
+    /// Count one sample, moving the LFOs forward every `STEP_SAMPLES`. Returns true when they
+    /// moved and the scales need to be applied again.
+    pub fn tick(&mut self, rng: &mut XorShiftRng, sample_rate: f32) -> bool {
+        self.samples_until_step -= 1;
+        if self.samples_until_step > 0 {
+            return false;
+        }
+        self.samples_until_step = STEP_SAMPLES;
+        self.drive.advance(rng, STEP_SAMPLES, sample_rate);
+        self.crosstalk.advance(rng, STEP_SAMPLES, sample_rate);
+        self.linearizer.advance(rng, STEP_SAMPLES, sample_rate);
+        true
+    }
+
+    pub fn drive_scale(&self, life: f32) -> f32 {
+        1.0 + life * DRIVE_DEPTH * self.drive.value()
+    }
+
+    pub fn crosstalk_scale(&self, life: f32) -> f32 {
+        1.0 + life * CROSSTALK_DEPTH * self.crosstalk.value()
+    }
+
+    pub fn linearizer_scale(&self, life: f32) -> f32 {
+        1.0 + life * LINEARIZER_DEPTH * self.linearizer.value()
+    }
+}
@@ -0,0 +1,134 @@
+// ducker.rs - Ardura 2025
+// Rhythmic ducking with a fixed envelope per trigger, for that pumping sound
+
+/// Transient trigger: the fast envelope has to jump this far over the slow one (about 6 dB)
+const TRIGGER_RATIO: f32 = 2.0;
+/// The fast envelope has to fall back under this ratio before another trigger can happen
+const REARM_RATIO: f32 = 1.2;
+/// Ignore transients quieter than this (about -40 dBFS)
+const TRIGGER_FLOOR: f32 = 0.01;
+
+/// Ducks the signal with the same shape on every trigger: a quick dip to `1 - depth` and an
+/// exponential recovery set by the release. Unlike the compressor the amount doesn't depend on
+/// level. Triggers come from the host's beat grid while it's playing, or from transients in the
+/// detector level otherwise.
+pub struct RhythmicDucker {
+    sample_rate: f32,
+    depth: f32,
+    release_ms: f32,
+    release_coeff: f32,
+    // Short smoothing on the gain so the dip doesn't click
+    smoothing_coeff: f32,
+
+    // 1.0 right after a trigger, decays to 0.0
+    envelope: f32,
+    gain: f32,
+
+    // Tempo sync
+    last_beat: Option<i64>,
+
+    // Transient detection
+    fast_envelope: f32,
+    slow_envelope: f32,
+    fast_coeff: f32,
+    slow_coeff: f32,
+    armed: bool,
+}
+
+impl RhythmicDucker {
+    pub fn new(sample_rate: f32) -> Self {
+        let release_ms = 200.0;
+        Self {
+            sample_rate,
+            depth: 0.0,
+            release_ms,
+            release_coeff: Self::time_coefficient(release_ms, sample_rate),
+            smoothing_coeff: Self::time_coefficient(1.0, sample_rate),
+            envelope: 0.0,
+            gain: 1.0,
+            last_beat: None,
+            fast_envelope: 0.0,
+            slow_envelope: 0.0,
+            fast_coeff: Self::time_coefficient(1.0, sample_rate),
+            slow_coeff: Self::time_coefficient(50.0, sample_rate),
+            armed: true,
+        }
+    }
+
+    fn time_coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+        (-1.0 / (time_ms * 0.001 * sample_rate)).exp()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        self.release_coeff = Self::time_coefficient(self.release_ms, sample_rate);
+        self.smoothing_coeff = Self::time_coefficient(1.0, sample_rate);
+        self.fast_coeff = Self::time_coefficient(1.0, sample_rate);
+        self.slow_coeff = Self::time_coefficient(50.0, sample_rate);
+    }
+
+    /// How far the signal dips on each trigger, 0.0 to 1.0
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+
+    /// Recovery time after each trigger
+    pub fn set_release(&mut self, release_ms: f32) {
+        if release_ms != self.release_ms {
+            self.release_ms = release_ms;
+            self.release_coeff = Self::time_coefficient(release_ms, self.sample_rate);
+        }
+    }
+
+    /// Get the ducking gain for one sample. `beat_position` is the host position in beats when
+    /// the transport is running, `level` is the detector input for transient triggering.
+    pub fn process(&mut self, beat_position: Option<f64>, level: f32) -> f32 {
+        let triggered = match beat_position {
+            Some(beats) => {
+                // Starting the transport mid beat waits for the next beat instead of ducking
+                // right away, starting on a beat still ducks it
+                if self.last_beat.is_none() {
+                    self.last_beat = Some(beats.ceil() as i64 - 1);
+                }
+                let beat = beats.floor() as i64;
+                let new_beat = self.last_beat != Some(beat);
+                self.last_beat = Some(beat);
+                new_beat
+            }
+            None => {
+                self.last_beat = None;
+                self.detect_transient(level)
+            }
+        };
+
+        if triggered {
+            self.envelope = 1.0;
+        }
+
+        let target_gain = 1.0 - self.depth * self.envelope;
+        self.envelope *= self.release_coeff;
+        self.gain = target_gain + (self.gain - target_gain) * self.smoothing_coeff;
+        self.gain
+    }
+
+    fn detect_transient(&mut self, level: f32) -> bool {
+        let level = level.abs();
+        self.fast_envelope = level + (self.fast_envelope - level) * self.fast_coeff;
+        self.slow_envelope = level + (self.slow_envelope - level) * self.slow_coeff;
+
+        if !self.armed {
+            self.armed = self.fast_envelope < self.slow_envelope * REARM_RATIO;
+            false
+        } else if self.fast_envelope > self.slow_envelope * TRIGGER_RATIO
+            && self.fast_envelope > TRIGGER_FLOOR
+        {
+            self.armed = false;
+            true
+        } else {
+            false
+        }
+    }
+}
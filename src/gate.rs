@@ -0,0 +1,102 @@
+// gate.rs - Ardura 2025
+// Noise floor gate ahead of the console so the saturation doesn't bring up hiss
+
+use nih_plug::util;
+
+/// Threshold at the bottom of the control, treated as off
+pub const GATE_OFF_DB: f32 = -90.0;
+/// How far the gate turns down when closed. Not a full mute so tails don't chop off.
+const RANGE_DB: f32 = -24.0;
+/// The gate closes this far below the threshold, so it doesn't chatter on signal sitting on it
+const HYSTERESIS_DB: f32 = 3.0;
+
+/// Stereo linked downward gate with fixed gentle timing. The detector is a peak envelope of the
+/// louder channel, the gain glides open quickly and closes slowly.
+pub struct NoiseGate {
+    sample_rate: f32,
+    open_threshold: f32,
+    close_threshold: f32,
+    open: bool,
+    closed_gain: f32,
+    envelope: f32,
+    gain: f32,
+    envelope_release_coeff: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+}
+
+impl NoiseGate {
+    /// Detector envelope release, long enough to ride over the waveform's zero crossings
+    const ENVELOPE_RELEASE_MS: f32 = 20.0;
+    const ATTACK_MS: f32 = 1.0;
+    const RELEASE_MS: f32 = 150.0;
+
+    pub fn new(sample_rate: f32) -> Self {
+        let mut gate = Self {
+            sample_rate,
+            open_threshold: 0.0,
+            close_threshold: 0.0,
+            open: true,
+            closed_gain: util::db_to_gain(RANGE_DB),
+            envelope: 0.0,
+            gain: 1.0,
+            envelope_release_coeff: 0.0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+        };
+        gate.update_coefficients();
+        gate
+    }
+
+    fn coefficient(time_ms: f32, sample_rate: f32) -> f32 {
+        (-1.0 / (time_ms * 0.001 * sample_rate)).exp()
+    }
+
+    fn update_coefficients(&mut self) {
+        self.envelope_release_coeff = Self::coefficient(Self::ENVELOPE_RELEASE_MS, self.sample_rate);
+        self.attack_coeff = Self::coefficient(Self::ATTACK_MS, self.sample_rate);
+        self.release_coeff = Self::coefficient(Self::RELEASE_MS, self.sample_rate);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.update_coefficients();
+        }
+    }
+
+    /// Set the threshold in dBFS, `GATE_OFF_DB` or lower turns the gate off
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        if threshold_db <= GATE_OFF_DB {
+            self.open_threshold = 0.0;
+            self.close_threshold = 0.0;
+        } else {
+            self.open_threshold = util::db_to_gain(threshold_db);
+            self.close_threshold = util::db_to_gain(threshold_db - HYSTERESIS_DB);
+        }
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let level = left.abs().max(right.abs());
+        self.envelope = if level > self.envelope {
+            level
+        } else {
+            level + (self.envelope - level) * self.envelope_release_coeff
+        };
+
+        if self.envelope > self.open_threshold {
+            self.open = true;
+        } else if self.envelope < self.close_threshold {
+            self.open = false;
+        }
+
+        let (target, coeff) = if self.open {
+            (1.0, self.attack_coeff)
+        } else {
+            (self.closed_gain, self.release_coeff)
+        };
+        self.gain = target + (self.gain - target) * coeff;
+
+        (left * self.gain, right * self.gain)
+    }
+}
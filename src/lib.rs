@@ -1,7 +1,15 @@
 #![allow(non_snake_case)]
-use analog_console::{AnalogConsoleProcessor, SaturationType};
-use auto_compressor::SimpleAutoCompressor;
-use db_meter::DBMeter;
+use analog_console::{AnalogConsoleProcessor, CrosstalkModel, DriveCurve, InputCoupling, Precision, SaturationBand, SaturationType};
+use auto_compressor::{CompDirection, DetectorHighpass, MakeupMode, SimpleAutoCompressor};
+use clipper::PeakClipper;
+use drift::ConsoleDrift;
+use gate::NoiseGate;
+use ducker::RhythmicDucker;
+use rng::XorShiftRng;
+use stats::{BlockStats, BlockStatsCollector};
+use stereo::{BassMono, ChannelMask, Correlation, HaasDelay};
+use warmth::HarmonicWarmth;
+use db_meter::{CrestMeter, DBMeter, MeterMode};
 use nih_plug::prelude::*;
 use nih_plug_egui::{
     create_egui_editor,
@@ -9,10 +17,20 @@ use nih_plug_egui::{
     widgets, EguiState,
 };
 mod BoolButton;
-use std::sync::Arc;
+use std::sync::{atomic::{AtomicBool, AtomicU64}, Arc};
 mod db_meter;
-mod analog_console;
-mod auto_compressor;
+pub mod analog_console;
+pub mod auto_compressor;
+mod ducker;
+pub mod rng;
+mod clipper;
+mod drift;
+mod gate;
+#[cfg(debug_assertions)]
+mod self_test;
+pub mod stats;
+mod stereo;
+mod warmth;
 
 /**************************************************
  * UnderBrush v1.0.1 by Ardura
@@ -29,14 +47,68 @@ const ORANGE: Color32 = Color32::from_rgb(188, 108, 37);
 /// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
 const PEAK_METER_DECAY_MS: f64 = 100.0;
 
+/// Block peak below this counts as silence for tail reporting (about -120 dBFS)
+const SILENCE_THRESHOLD: f32 = 0.000001;
+
+/// Full travel of the gain reduction meter
+const GR_METER_RANGE_DB: f32 = 24.0;
+
+/// Averaging time of the input level the gain staging hint is based on
+const TRIM_ANALYSIS_SECONDS: f32 = 3.0;
+
+/// Below this average level (dBFS) there's not enough signal to suggest a trim
+const TRIM_ANALYSIS_FLOOR_DB: f32 = -60.0;
+
+/// Range of the meters below the reference level, in dB
+const METER_RANGE_DB: f32 = 60.0;
+
+/// How close (in normalized slider travel) a drag has to get to a detent to snap to it
+const DETENT_ZONE: f32 = 0.02;
+
+/// Snap a `ParamSlider` being dragged onto the nearest detent value. Holding Alt drags freely,
+/// and Shift's fine dragging is left alone. Only the editor snaps, automation stays continuous.
+fn snap_to_detents(ui: &egui::Ui, response: &egui::Response, param: &FloatParam, setter: &ParamSetter, detents: &[f32]) {
+    let modifiers = ui.input(|i| i.modifiers);
+    if !response.dragged() || modifiers.alt || modifiers.shift {
+        return;
+    }
+    if let Some(pointer) = response.interact_pointer_pos() {
+        let dragged_to = ((pointer.x - response.rect.left()) / response.rect.width()).clamp(0.0, 1.0);
+        for &detent in detents {
+            if (dragged_to - param.preview_normalized(detent)).abs() < DETENT_ZONE {
+                setter.set_parameter(param, detent);
+                break;
+            }
+        }
+    }
+}
+
 pub struct UnderBrush {
     params: Arc<UnderBrushParams>,
     // The current data for the different meters
     out_meter: Arc<AtomicF32>,
     in_meter: Arc<AtomicF32>,
+    // Compressor gain reduction in dB, or the boost when compressing upward. Also when only
+    // analyzing
+    gr_meter: Arc<AtomicF32>,
+    // Compressor auto-threshold as linear gain for the input meter marker
+    comp_threshold: Arc<AtomicF32>,
+    // Latched when the plugin input goes over 0 dBFS, reset from the editor
+    in_clip: Arc<AtomicBool>,
+    // Slow average input level in dBFS (before the trim) for the gain staging hint
+    in_average: Arc<AtomicF32>,
+    in_mean_square: f32,
+    // Crest factor readouts in dB
+    in_crest: Arc<AtomicF32>,
+    out_crest: Arc<AtomicF32>,
+    in_crest_meter: db_meter::CrestMeter,
+    out_crest_meter: db_meter::CrestMeter,
     // normalize the peak meter's response based on the sample rate with this
     out_meter_decay_weight: f32,
 
+    // Set when the host renders offline, quality settings get overridden for the bounce
+    offline_render: bool,
+
     // Slew History
     prev_slew_l: f32,
     prev_slew_r: f32,
@@ -46,6 +118,45 @@ pub struct UnderBrush {
 
     // Compression
     compressor: auto_compressor::SimpleAutoCompressor,
+    detector_hpf: auto_compressor::DetectorHighpass,
+
+    // Noise gate on the console input
+    noise_gate: gate::NoiseGate,
+
+    // Low order harmonics after the console
+    warmth_left: warmth::HarmonicWarmth,
+    warmth_right: warmth::HarmonicWarmth,
+
+    // Rhythmic ducking
+    ducker: ducker::RhythmicDucker,
+
+    // Soft clip ahead of the dynamics stage
+    pre_clipper: clipper::PeakClipper,
+
+    // Output clipper
+    clipper: clipper::PeakClipper,
+
+    // Soft limiter on the wet path ahead of the mix
+    wet_limiter: clipper::PeakClipper,
+
+    // Output stereo processing
+    bass_mono: stereo::BassMono,
+    haas: stereo::HaasDelay,
+    correlation: stereo::Correlation,
+    // Smoothed output correlation for the Haas mono compatibility readout
+    correlation_meter: Arc<AtomicF32>,
+
+    // Shared randomness, seeded from the persisted `rng_seed`
+    rng: rng::XorShiftRng,
+
+    // Slow wandering of the console parameters
+    drift: drift::ConsoleDrift,
+    // Drive after the drift, what the saturation coupling pushes from
+    drifted_drive: f32,
+
+    // Statistics of the last processed block
+    block_stats: stats::BlockStats,
+    stats_collector: stats::BlockStatsCollector,
 }
 
 #[derive(Params)]
@@ -54,6 +165,34 @@ struct UnderBrushParams {
     #[persist = "editor-state"]
     editor_state: Arc<EguiState>,
 
+    /// Input trim ahead of everything else
+    #[id = "input_trim"]
+    pub input_trim: FloatParam,
+
+    /// Noise gate threshold on the console input, the bottom of the range is off
+    #[id = "noise_gate"]
+    pub noise_gate: FloatParam,
+
+    /// Utility: Swap Left/Right
+    #[id = "swap_lr"]
+    pub swap_lr: BoolParam,
+
+    /// Utility: Invert Left Polarity
+    #[id = "invert_l"]
+    pub invert_l: BoolParam,
+
+    /// Utility: Invert Right Polarity
+    #[id = "invert_r"]
+    pub invert_r: BoolParam,
+
+    /// Utility: Sum to Mono
+    #[id = "mono_sum"]
+    pub mono_sum: BoolParam,
+
+    /// Output channels that get processed, the rest pass through clean
+    #[id = "channel_mask"]
+    pub channel_mask: EnumParam<ChannelMask>,
+
     /// Slew Limiting
     #[id = "slew"]
     pub slew: FloatParam,
@@ -62,10 +201,54 @@ struct UnderBrushParams {
     #[id = "drive"]
     pub drive: FloatParam,
 
+    /// Extended drive range for sound design
+    #[id = "extreme"]
+    pub extreme: BoolParam,
+
+    /// Console Drive Curve
+    #[id = "drive_curve"]
+    pub drive_curve: EnumParam<DriveCurve>,
+
     /// Console Saturation Type
     #[id = "type"]
     pub sat_type: EnumParam<SaturationType>,
 
+    /// Anti-aliased polynomial saturation
+    #[id = "antialias"]
+    pub antialias: BoolParam,
+
+    /// Band the saturation is focused on
+    #[id = "sat_band"]
+    pub sat_band: EnumParam<SaturationBand>,
+
+    /// Crossover for band focused saturation
+    #[id = "sat_band_freq"]
+    pub sat_band_freq: FloatParam,
+
+    /// 2nd harmonic warmth amount
+    #[id = "warmth_2nd"]
+    pub warmth_2nd: FloatParam,
+
+    /// 3rd harmonic warmth amount
+    #[id = "warmth_3rd"]
+    pub warmth_3rd: FloatParam,
+
+    /// Amount of slow random wandering on drive, crosstalk and linearizer frequency
+    #[id = "life"]
+    pub life: FloatParam,
+
+    /// Transient boost ahead of the saturation
+    #[id = "transient_enhance"]
+    pub transient_enhance: FloatParam,
+
+    /// Crosstalk coupling model
+    #[id = "crosstalk_model"]
+    pub crosstalk_model: EnumParam<CrosstalkModel>,
+
+    /// Input Coupling
+    #[id = "coupling"]
+    pub coupling: EnumParam<InputCoupling>,
+
     /// Linearizer Frequency
     #[id = "Linearizer Hz"]
     pub l_hz: FloatParam,
@@ -74,10 +257,79 @@ struct UnderBrushParams {
     #[id = "Comp"]
     pub comp: BoolParam,
 
+    /// Compressor detector and meters only, the audio stays uncompressed
+    #[id = "comp_analyze"]
+    pub comp_analyze: BoolParam,
+
+    /// Compressor Direction
+    #[id = "comp_direction"]
+    pub comp_direction: EnumParam<CompDirection>,
+
+    /// Compressor Makeup Mode
+    #[id = "makeup_mode"]
+    pub makeup_mode: EnumParam<MakeupMode>,
+
+    /// Compressor Auto Makeup Lag
+    #[id = "makeup_lag"]
+    pub makeup_lag: FloatParam,
+
+    /// Compressor parallel blend. The compressor taps a copy of the saturated signal and this
+    /// blends it back in, 1.0 is the plain serial chain
+    #[id = "comp_mix"]
+    pub comp_mix: FloatParam,
+
+    /// Compressor detector listens to the sidechain input
+    #[id = "sidechain"]
+    pub sidechain: BoolParam,
+
+    /// High-pass on the compressor detector, 0 is off
+    #[id = "detector_hpf"]
+    pub detector_hpf: FloatParam,
+
+    /// Solo the detector signal
+    #[id = "sc_listen"]
+    pub sc_listen: BoolParam,
+
+    /// Soft clip ahead of the compressor, 0 is off
+    #[id = "pre_clip"]
+    pub pre_clip: FloatParam,
+
+    /// Gain Reduction to Drive Coupling
+    #[id = "sat_coupling"]
+    pub sat_coupling: FloatParam,
+
+    /// Rhythmic Duck Depth, 0 is off
+    #[id = "duck_depth"]
+    pub duck_depth: FloatParam,
+
+    /// Rhythmic Duck Release
+    #[id = "duck_release"]
+    pub duck_release: FloatParam,
+
     /// Clipper
     #[id = "Clip at 0db"]
     pub clip: BoolParam,
 
+    /// Clipper Threshold
+    #[id = "clip_threshold"]
+    pub clip_threshold: FloatParam,
+
+    /// Clipper Knee
+    #[id = "clip_knee"]
+    pub clip_knee: FloatParam,
+
+    /// Wet Path Limiter
+    #[id = "wet_limit"]
+    pub wet_limit: BoolParam,
+
+    /// Wet Path Limiter Ceiling
+    #[id = "wet_ceiling"]
+    pub wet_ceiling: FloatParam,
+
+    /// Output Clipper Release, 0 is a sample-accurate clip
+    #[id = "limiter_release"]
+    pub limiter_release: FloatParam,
+
     /// Console Wet/Dry
     #[id = "mix"]
     pub mix: FloatParam,
@@ -86,9 +338,527 @@ struct UnderBrushParams {
     #[id = "gain"]
     pub gain: FloatParam,
 
+    /// Bass Mono Crossover, 0 is off
+    #[id = "bass_mono_freq"]
+    pub bass_mono_freq: FloatParam,
+
+    /// Haas delay of the right channel in samples, 0 is off
+    #[id = "haas"]
+    pub haas: IntParam,
+
     /// Master out
     #[id = "Master Out"]
     pub master_out: FloatParam,
+
+    /// Precision of the recursive filters (DC blocker, allpass, compressor envelope)
+    #[id = "precision"]
+    pub precision: EnumParam<Precision>,
+
+    /// Meter update rate
+    #[id = "meter_mode"]
+    pub meter_mode: EnumParam<MeterMode>,
+
+    /// Seed for the random generators, persisted so a session recalls identically
+    #[persist = "rng-seed"]
+    rng_seed: Arc<AtomicU64>,
+
+    /// Peak capture, frozen while held. Persisted so the readouts survive reopening
+    #[persist = "peak-hold"]
+    peak_hold: Arc<AtomicBool>,
+
+    /// dBFS level that reads as 0 on the meters, e.g. -18 for a -18 dBFS = 0 VU alignment
+    #[persist = "meter-reference"]
+    meter_reference: Arc<AtomicF32>,
+    #[persist = "peak-in"]
+    peak_in: Arc<AtomicF32>,
+    #[persist = "peak-out"]
+    peak_out: Arc<AtomicF32>,
+    #[persist = "peak-gr"]
+    peak_gr: Arc<AtomicF32>,
+}
+
+/// Everything the chain takes from the parameters, read once at the start of each block
+struct BlockSettings {
+    sample_rate: f32,
+    // Input trim as linear gain
+    input_trim: f32,
+    swap_lr: bool,
+    invert_l: bool,
+    invert_r: bool,
+    mono_sum: bool,
+    noise_gate_db: f32,
+    drive: f32,
+    extreme: bool,
+    drive_curve: DriveCurve,
+    life: f32,
+    l_hz: f32,
+    sat_type: SaturationType,
+    antialias: bool,
+    sat_band: SaturationBand,
+    sat_band_freq: f32,
+    transient_enhance: f32,
+    crosstalk_model: CrosstalkModel,
+    input_coupling: InputCoupling,
+    precision: Precision,
+    warmth_2nd: f32,
+    warmth_3rd: f32,
+    slew: f32,
+    comp: bool,
+    comp_analyze: bool,
+    comp_direction: CompDirection,
+    makeup_mode: MakeupMode,
+    makeup_lag: f32,
+    comp_mix: f32,
+    detector_hpf: f32,
+    sc_listen: bool,
+    pre_clip: f32,
+    sat_coupling: f32,
+    duck_depth: f32,
+    duck_release: f32,
+    // Output gain as linear gain
+    gain: f32,
+    clip: bool,
+    clip_threshold_db: f32,
+    clip_knee: f32,
+    clip_release: f32,
+    wet_limit: bool,
+    wet_ceiling_db: f32,
+    mix: f32,
+    bass_mono_freq: f32,
+    haas: usize,
+    channel_mask: ChannelMask,
+}
+
+impl UnderBrush {
+    /// Set and persist the random seed, e.g. to make randomized features reproducible in tests
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.params.rng_seed.store(seed, std::sync::atomic::Ordering::Relaxed);
+        self.rng.set_seed(seed);
+    }
+
+    /// Set the drifted drive, crosstalk and linearizer frequency on the console
+    fn apply_drift(&mut self, settings: &BlockSettings) {
+        let life = settings.life;
+        self.drifted_drive = settings.drive * self.drift.drive_scale(life);
+        self.console.set_drive(self.drifted_drive);
+        self.console.set_crosstalk(0.03 * self.drift.crosstalk_scale(life));
+        self.console
+            .set_phase_linearizer_freq(settings.l_hz * self.drift.linearizer_scale(life));
+    }
+
+    fn block_settings(&self, sample_rate: f32) -> BlockSettings {
+        let params = &self.params;
+        BlockSettings {
+            sample_rate,
+            input_trim: util::db_to_gain(params.input_trim.value()),
+            swap_lr: params.swap_lr.value(),
+            invert_l: params.invert_l.value(),
+            invert_r: params.invert_r.value(),
+            mono_sum: params.mono_sum.value(),
+            noise_gate_db: params.noise_gate.value(),
+            drive: params.drive.value(),
+            extreme: params.extreme.value(),
+            drive_curve: params.drive_curve.value(),
+            life: params.life.value(),
+            l_hz: params.l_hz.value(),
+            sat_type: params.sat_type.value(),
+            antialias: params.antialias.value(),
+            sat_band: params.sat_band.value(),
+            sat_band_freq: params.sat_band_freq.value(),
+            transient_enhance: params.transient_enhance.value(),
+            crosstalk_model: params.crosstalk_model.value(),
+            input_coupling: params.coupling.value(),
+            // Offline bounces always get the 64-bit filters, realtime follows the Precision setting
+            precision: if self.offline_render {
+                Precision::Double
+            } else {
+                params.precision.value()
+            },
+            warmth_2nd: params.warmth_2nd.value(),
+            warmth_3rd: params.warmth_3rd.value(),
+            slew: params.slew.value(),
+            comp: params.comp.value(),
+            comp_analyze: params.comp_analyze.value(),
+            comp_direction: params.comp_direction.value(),
+            makeup_mode: params.makeup_mode.value(),
+            makeup_lag: params.makeup_lag.value(),
+            comp_mix: params.comp_mix.value(),
+            detector_hpf: params.detector_hpf.value(),
+            sc_listen: params.sc_listen.value(),
+            pre_clip: params.pre_clip.value(),
+            sat_coupling: params.sat_coupling.value(),
+            duck_depth: params.duck_depth.value(),
+            duck_release: params.duck_release.value(),
+            gain: util::db_to_gain(params.gain.value()),
+            clip: params.clip.value(),
+            clip_threshold_db: params.clip_threshold.value(),
+            clip_knee: params.clip_knee.value(),
+            clip_release: params.limiter_release.value(),
+            wet_limit: params.wet_limit.value(),
+            wet_ceiling_db: params.wet_ceiling.value(),
+            mix: params.mix.value(),
+            bass_mono_freq: params.bass_mono_freq.value(),
+            haas: params.haas.value().max(0) as usize,
+            channel_mask: params.channel_mask.value(),
+        }
+    }
+
+    /// Hand the block's settings to the DSP before its first `process_frame`
+    fn start_block(&mut self, settings: &BlockSettings) {
+        let sample_rate = settings.sample_rate;
+        self.console.set_sample_rate(sample_rate);
+        self.console.set_drive_curve(settings.drive_curve);
+        self.console.set_extreme(settings.extreme);
+
+        // Console drift, advanced per sample in `process_frame`
+        self.apply_drift(settings);
+        self.console.set_saturation_type(settings.sat_type);
+        self.console.set_antialiasing(settings.antialias);
+        self.console.set_saturation_band(settings.sat_band);
+        self.console.set_band_frequency(settings.sat_band_freq);
+        self.noise_gate.set_sample_rate(sample_rate);
+        self.noise_gate.set_threshold_db(settings.noise_gate_db);
+
+        self.warmth_left.set_sample_rate(sample_rate);
+        self.warmth_right.set_sample_rate(sample_rate);
+        self.warmth_left.set_amounts(settings.warmth_2nd, settings.warmth_3rd);
+        self.warmth_right.set_amounts(settings.warmth_2nd, settings.warmth_3rd);
+        self.console.set_transient_enhance(settings.transient_enhance);
+        self.console.set_crosstalk_model(settings.crosstalk_model);
+        self.console.set_precision(settings.precision);
+        self.warmth_left.set_precision(settings.precision);
+        self.warmth_right.set_precision(settings.precision);
+        self.console.set_input_coupling(settings.input_coupling);
+
+        self.compressor.set_sample_rate(sample_rate);
+        self.compressor.set_precision(settings.precision);
+        self.compressor.set_direction(settings.comp_direction);
+        self.compressor.set_makeup_mode(settings.makeup_mode);
+        self.compressor.set_makeup_lag(settings.makeup_lag);
+        self.compressor.set_analyze_only(settings.comp_analyze);
+        self.detector_hpf.set_sample_rate(sample_rate);
+        self.detector_hpf.set_frequency(settings.detector_hpf);
+
+        self.clipper.set_threshold_db(settings.clip_threshold_db);
+        self.clipper.set_knee(settings.clip_knee);
+        self.clipper.set_release(sample_rate, settings.clip_release);
+
+        // Gentle fixed knee so the wet limiter only rounds off the top
+        self.wet_limiter.set_threshold_db(settings.wet_ceiling_db);
+        self.wet_limiter.set_knee(0.5);
+        // Pre clip lowers its ceiling to -12 dB and widens its knee to half the ceiling at full
+        // amount. Both scale together so small amounts only round off the very top.
+        self.pre_clipper.set_threshold_db(-12.0 * settings.pre_clip);
+        self.pre_clipper.set_knee(0.5 * settings.pre_clip);
+
+        self.haas.set_delay(settings.haas);
+        self.bass_mono.set_sample_rate(sample_rate);
+        if settings.bass_mono_freq > 0.0 {
+            self.bass_mono.set_frequency(settings.bass_mono_freq);
+        }
+
+        self.ducker.set_sample_rate(sample_rate);
+        self.ducker.set_depth(settings.duck_depth);
+        self.ducker.set_release(settings.duck_release);
+    }
+
+    /// Run one sample through the whole chain. `sidechain` is the sidechain input when it's
+    /// enabled and connected, `beat_position` the host position in beats while it's playing.
+    /// Returns the dry signal after the trim and utility stage along with the output.
+    fn process_frame(
+        &mut self,
+        settings: &BlockSettings,
+        (input_l, input_r): (f32, f32),
+        is_stereo: bool,
+        sidechain: Option<(f32, f32)>,
+        beat_position: Option<f64>,
+    ) -> ((f32, f32), (f32, f32)) {
+        let localthreshold = settings.slew / (settings.sample_rate / 44100.0);
+        let comp_running = settings.comp || settings.comp_analyze;
+        let coupling_on = settings.sat_coupling > 0.0 && settings.comp && !settings.comp_analyze;
+
+        // Always advanced so the random sequence doesn't depend on Life
+        if self.drift.tick(&mut self.rng, settings.sample_rate) {
+            self.apply_drift(settings);
+        }
+
+        let mut out_l = input_l * settings.input_trim;
+        let mut out_r = input_r * settings.input_trim;
+
+        // Utility stage before the console so the dry path gets it too.
+        // Swap happens first, so the inverts act on the swapped channels.
+        if is_stereo {
+            if settings.swap_lr {
+                std::mem::swap(&mut out_l, &mut out_r);
+            }
+            if settings.invert_l {
+                out_l = -out_l;
+            }
+            if settings.invert_r {
+                out_r = -out_r;
+            }
+            if settings.mono_sum {
+                let mid = (out_l + out_r) * 0.5;
+                out_l = mid;
+                out_r = mid;
+            }
+        } else if settings.invert_l {
+            out_l = -out_l;
+            out_r = out_l;
+        }
+
+        let dry_left = out_l;
+        let dry_right = out_r;
+
+        // Compression pushes the console harder, using the last sample's gain reduction
+        if coupling_on {
+            let coupled_db = settings.sat_coupling * self.compressor.gain_reduction_db.max(0.0);
+            self.console.set_drive(self.drifted_drive * util::db_to_gain(coupled_db));
+        }
+
+        // Gate the noise floor before the saturation can amplify it, the dry path stays as is
+        if settings.noise_gate_db > gate::GATE_OFF_DB {
+            (out_l, out_r) = self.noise_gate.process(out_l, out_r);
+        }
+
+        // Main Processing
+        (out_l, out_r) = self.console.process(out_l, out_r);
+
+        // Harmonic warmth for the quiet parts the saturation leaves linear
+        if settings.warmth_2nd > 0.0 || settings.warmth_3rd > 0.0 {
+            out_l = self.warmth_left.process(out_l);
+            out_r = self.warmth_right.process(out_r);
+        }
+
+        // Slew limiting
+        let mut clamp = out_l - self.prev_slew_l;
+        let mut slew_engaged = clamp.abs() > localthreshold;
+        if clamp > localthreshold {
+            out_l = self.prev_slew_l + localthreshold;
+        }
+        if -clamp > localthreshold {
+            out_l = self.prev_slew_l - localthreshold;
+        }
+        self.prev_slew_l = out_l;
+
+        clamp = out_r - self.prev_slew_r;
+        slew_engaged |= clamp.abs() > localthreshold;
+        if clamp > localthreshold {
+            out_r = self.prev_slew_r + localthreshold;
+        }
+        if -clamp > localthreshold {
+            out_r = self.prev_slew_r - localthreshold;
+        }
+        self.prev_slew_r = out_r;
+        if slew_engaged {
+            self.stats_collector.slew_engaged();
+        }
+
+        // Tame spikes in the compressor's copy before it hears them or processes them
+        let (comp_in_l, comp_in_r) = if comp_running && settings.pre_clip > 0.0 {
+            (self.pre_clipper.process(out_l), self.pre_clipper.process(out_r))
+        } else {
+            (out_l, out_r)
+        };
+
+        // Detector key: the compressor's own input or the sidechain, through the detector HPF
+        let (key_l, key_r) = match sidechain {
+            Some((key_l, key_r)) => self.detector_hpf.process(key_l, key_r),
+            None => self.detector_hpf.process(comp_in_l, comp_in_r),
+        };
+
+        // The compressor works on a copy of the saturated signal which gets blended back in,
+        // so saturation always reaches the output and only the dynamics are parallel
+        if comp_running {
+            let comp_l = self.compressor.process_keyed(comp_in_l, key_l);
+            let comp_r = self.compressor.process_keyed(comp_in_r, key_r);
+            self.stats_collector.add_gain_reduction(self.compressor.gain_reduction_db);
+            out_l += (comp_l - out_l) * settings.comp_mix;
+            out_r += (comp_r - out_r) * settings.comp_mix;
+        }
+
+        if settings.duck_depth > 0.0 {
+            // Transients come from the sidechain key when there is one, the dry input otherwise
+            let duck_level = if sidechain.is_some() {
+                (key_l + key_r) * 0.5
+            } else {
+                (dry_left + dry_right) * 0.5
+            };
+            let duck_gain = self.ducker.process(beat_position, duck_level);
+            out_l *= duck_gain;
+            out_r *= duck_gain;
+        }
+
+        out_l *= settings.gain;
+        out_r *= settings.gain;
+
+        // Safety for our ears
+        if settings.clip {
+            let (unclipped_l, unclipped_r) = (out_l, out_r);
+            (out_l, out_r) = self.clipper.process_stereo(out_l, out_r);
+            if out_l != unclipped_l || out_r != unclipped_r {
+                self.stats_collector.clipper_engaged();
+            }
+        }
+
+        // Tame the wet path on its own so the result doesn't depend on Mix
+        if settings.wet_limit {
+            out_l = self.wet_limiter.process(out_l);
+            out_r = self.wet_limiter.process(out_r);
+        }
+
+        // Mix dry/wet
+        out_l = (1.0 - settings.mix) * dry_left + settings.mix * out_l;
+        out_r = (1.0 - settings.mix) * dry_right + settings.mix * out_r;
+
+        // Mono the low end after the mix so dry signal can't bring stereo bass back
+        if is_stereo && settings.bass_mono_freq > 0.0 {
+            (out_l, out_r) = self.bass_mono.process(out_l, out_r);
+        }
+
+        // Haas widening on the final output, dry included
+        if is_stereo && settings.haas > 0 {
+            (out_l, out_r) = self.haas.process(out_l, out_r);
+            self.correlation.add(out_l, out_r);
+        }
+
+        // Masked out channels get the untouched input. The chain reports no latency, so the
+        // clean side stays time aligned without a delay.
+        if is_stereo {
+            match settings.channel_mask {
+                ChannelMask::Both => {}
+                ChannelMask::Left => out_r = input_r,
+                ChannelMask::Right => out_l = input_l,
+            }
+        }
+
+        // Listening replaces the output with exactly what the detector hears
+        if settings.sc_listen {
+            out_l = key_l;
+            out_r = key_r;
+        }
+
+        ((dry_left, dry_right), (out_l, out_r))
+    }
+
+    /// Statistics of the last processed block, see `BlockStats`
+    pub fn block_stats(&self) -> &BlockStats {
+        &self.block_stats
+    }
+}
+
+/// Map a dBFS level onto the meter's `[0, 1]` travel. The scale runs from `METER_RANGE_DB` below
+/// the reference up to 0 dBFS, so full scale always sits at the right edge
+fn meter_normalized(level_db: f32, reference_db: f32) -> f32 {
+    (level_db - reference_db + METER_RANGE_DB) / (METER_RANGE_DB - reference_db)
+}
+
+/// Meter readout relative to the reference, or plain dBFS when the reference is 0 dBFS
+fn meter_text(level_db: f32, reference_db: f32, label: &str) -> String {
+    let unit = if reference_db == 0.0 { "dBFS" } else { "VU" };
+    if level_db > util::MINUS_INFINITY_DB {
+        format!("{:.1} {unit} {label}", level_db - reference_db)
+    } else {
+        format!("-inf {unit} {label}")
+    }
+}
+
+/// Parameter value formatters in the shape nih-plug's `formatters` use
+type ValueToString = Arc<dyn Fn(f32) -> String + Send + Sync>;
+type StringToValue = Arc<dyn Fn(&str) -> Option<f32> + Send + Sync>;
+
+/// Frequency readout for filters that switch off at 0 Hz
+fn v2s_hz_or_off() -> ValueToString {
+    Arc::new(|value| {
+        if value <= 0.0 {
+            String::from("Off")
+        } else {
+            format!("{value:.0} Hz")
+        }
+    })
+}
+
+/// Drive as the saturation actually gets it: the stored multiplier bottoms out at 1x (0 dB) and
+/// Extreme scales it by `EXTREME_DRIVE_SCALE`
+fn v2s_drive_db(extreme_on: Arc<AtomicBool>) -> ValueToString {
+    Arc::new(move |value| {
+        let mut drive = value.max(1.0);
+        if extreme_on.load(std::sync::atomic::Ordering::Relaxed) {
+            drive *= analog_console::EXTREME_DRIVE_SCALE;
+        }
+        format!("{:.1}", util::gain_to_db(drive))
+    })
+}
+
+/// Parses the effective drive in dB back into the stored multiplier, with an optional "dB"
+fn s2v_drive_db(extreme_on: Arc<AtomicBool>) -> StringToValue {
+    Arc::new(move |string| {
+        let drive_db: f32 = string
+            .trim()
+            .trim_end_matches(|c: char| c.eq_ignore_ascii_case(&'d') || c.eq_ignore_ascii_case(&'b'))
+            .trim()
+            .parse()
+            .ok()?;
+        let mut drive = util::db_to_gain(drive_db);
+        if extreme_on.load(std::sync::atomic::Ordering::Relaxed) {
+            drive /= analog_console::EXTREME_DRIVE_SCALE;
+        }
+        Some(drive)
+    })
+}
+
+/// Gate threshold in dB, "Off" at the bottom of the range
+fn v2s_gate_db_or_off() -> ValueToString {
+    Arc::new(|value| {
+        if value <= gate::GATE_OFF_DB {
+            String::from("Off")
+        } else {
+            format!("{value:.1} dB")
+        }
+    })
+}
+
+/// Parses "Off" as the bottom of the gate range, anything else as a number with an optional "dB"
+fn s2v_gate_db_or_off() -> StringToValue {
+    Arc::new(|string| {
+        let string = string.trim();
+        if string.eq_ignore_ascii_case("off") {
+            Some(gate::GATE_OFF_DB)
+        } else {
+            string
+                .trim_end_matches(|c: char| c.eq_ignore_ascii_case(&'d') || c.eq_ignore_ascii_case(&'b'))
+                .trim()
+                .parse()
+                .ok()
+        }
+    })
+}
+
+/// Parses "Off" as 0 Hz, anything else as a number with an optional "Hz"
+fn s2v_hz_or_off() -> StringToValue {
+    Arc::new(|string| {
+        let string = string.trim();
+        if string.eq_ignore_ascii_case("off") {
+            Some(0.0)
+        } else {
+            string
+                .trim_end_matches(|c: char| c.eq_ignore_ascii_case(&'h') || c.eq_ignore_ascii_case(&'z'))
+                .trim()
+                .parse()
+                .ok()
+        }
+    })
+}
+
+/// Peak meter update: jump up to new peaks, otherwise decay towards the current amplitude
+fn update_peak_meter(meter: &AtomicF32, amplitude: f32, decay_weight: f32) {
+    let current_meter = meter.load(std::sync::atomic::Ordering::Relaxed);
+    let new_meter = if amplitude > current_meter {
+        amplitude
+    } else {
+        current_meter * decay_weight + amplitude * (1.0 - decay_weight)
+    };
+    meter.store(new_meter, std::sync::atomic::Ordering::Relaxed);
 }
 
 impl Default for UnderBrush {
@@ -96,59 +866,305 @@ impl Default for UnderBrush {
         Self {
             params: Arc::new(UnderBrushParams::default()),
             out_meter_decay_weight: 1.0,
+            offline_render: false,
             out_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
             in_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            gr_meter: Arc::new(AtomicF32::new(0.0)),
+            comp_threshold: Arc::new(AtomicF32::new(0.0)),
+            in_clip: Arc::new(AtomicBool::new(false)),
+            in_average: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            in_mean_square: 0.0,
+            in_crest: Arc::new(AtomicF32::new(0.0)),
+            out_crest: Arc::new(AtomicF32::new(0.0)),
+            in_crest_meter: CrestMeter::new(44100.0),
+            out_crest_meter: CrestMeter::new(44100.0),
             prev_slew_l: 0.0,
             prev_slew_r: 0.0,
             console: AnalogConsoleProcessor::new(44100.0),
             compressor: SimpleAutoCompressor::new(44100.0),
+            detector_hpf: DetectorHighpass::new(44100.0),
+            noise_gate: NoiseGate::new(44100.0),
+            warmth_left: HarmonicWarmth::new(44100.0),
+            warmth_right: HarmonicWarmth::new(44100.0),
+            ducker: RhythmicDucker::new(44100.0),
+            clipper: PeakClipper::new(),
+            wet_limiter: PeakClipper::new(),
+            pre_clipper: PeakClipper::new(),
+            bass_mono: BassMono::new(44100.0),
+            haas: HaasDelay::new(),
+            correlation: Correlation::new(),
+            correlation_meter: Arc::new(AtomicF32::new(1.0)),
+            rng: XorShiftRng::new(0),
+            drift: ConsoleDrift::new(),
+            drifted_drive: 1.0,
+            block_stats: BlockStats::default(),
+            stats_collector: BlockStatsCollector::new(),
         }
     }
 }
 
 impl Default for UnderBrushParams {
     fn default() -> Self {
+        // Extreme shifts what the Drive readout shows, the formatters follow it through here
+        let extreme_on = Arc::new(AtomicBool::new(false));
+
         Self {
-            editor_state: EguiState::from_size(250, 290),
+            editor_state: EguiState::from_size(250, 1382),
+            input_trim: FloatParam::new(
+                "Input Trim",
+                0.0,
+                FloatRange::Linear { min: -24.0, max: 24.0 },
+            )
+            .with_step_size(0.1)
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            noise_gate: FloatParam::new(
+                "Noise Gate",
+                gate::GATE_OFF_DB,
+                FloatRange::Linear { min: gate::GATE_OFF_DB, max: -30.0 },
+            )
+            .with_step_size(0.1)
+            .with_value_to_string(v2s_gate_db_or_off())
+            .with_string_to_value(s2v_gate_db_or_off()),
+            swap_lr: BoolParam::new("Swap L/R", false),
+            invert_l: BoolParam::new("Invert L", false),
+            invert_r: BoolParam::new("Invert R", false),
+            mono_sum: BoolParam::new("Mono", false),
+            channel_mask: EnumParam::new("Process", ChannelMask::Both),
             slew: FloatParam::new(
                 "Slew",
                 0.8,
                 FloatRange::Skewed { min: 0.00001, max: 1.0, factor: 0.3 },
             )
-            .with_step_size(0.00001),
+            .with_step_size(0.00001)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
             drive: FloatParam::new(
                 "Drive",
                 1.0,
-                FloatRange::Skewed { min: 0.00001, max: 10.0, factor: 0.3 },
+                // Below 1x did nothing, so the range starts there and is skewed to read evenly in
+                // dB. Older sessions with a stored value under 1x load as 1x, which sounds the same.
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 10.0,
+                    factor: FloatRange::gain_skew_factor(0.0, 20.0),
+                },
             )
-            .with_step_size(0.00001),
+            .with_step_size(0.00001)
+            // Stored as the multiplier for compatibility, shown and typed in dB
+            .with_unit(" dB")
+            .with_value_to_string(v2s_drive_db(extreme_on.clone()))
+            .with_string_to_value(s2v_drive_db(extreme_on.clone())),
+            extreme: BoolParam::new("Extreme", false).with_callback(Arc::new(move |extreme| {
+                extreme_on.store(extreme, std::sync::atomic::Ordering::Relaxed)
+            })),
+            drive_curve: EnumParam::new("Drive Curve", DriveCurve::Linear),
             sat_type: EnumParam::new("Type", SaturationType::Tape),
+            antialias: BoolParam::new("Anti-Alias", false),
+            sat_band: EnumParam::new("Sat Band", SaturationBand::Full),
+            sat_band_freq: FloatParam::new(
+                "Sat Split",
+                1000.0,
+                FloatRange::Skewed { min: 60.0, max: 8000.0, factor: 0.3 },
+            )
+            .with_step_size(1.0)
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            warmth_2nd: FloatParam::new(
+                "2nd Harmonic",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            warmth_3rd: FloatParam::new(
+                "3rd Harmonic",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            life: FloatParam::new(
+                "Life",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            transient_enhance: FloatParam::new(
+                "Punch",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            crosstalk_model: EnumParam::new("Crosstalk Model", CrosstalkModel::Resistive),
+            coupling: EnumParam::new("Coupling", InputCoupling::Ac),
             l_hz: FloatParam::new(
                 "Lin Hz",
                 150.0,
-                FloatRange::Linear { min: 20.0, max: 800.0 },
+                FloatRange::Skewed { min: 20.0, max: 800.0, factor: 0.3 },
             )
-            .with_step_size(1.0),
+            .with_step_size(0.1)
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
             comp: BoolParam::new("Compression", false),
+            comp_analyze: BoolParam::new("Analyze", false),
+            comp_direction: EnumParam::new("Direction", CompDirection::Downward),
+            makeup_mode: EnumParam::new("Makeup", MakeupMode::Fixed),
+            makeup_lag: FloatParam::new(
+                "Makeup Lag",
+                1000.0,
+                FloatRange::Skewed { min: 50.0, max: 5000.0, factor: 0.5 },
+            )
+            .with_step_size(1.0)
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+            comp_mix: FloatParam::new(
+                "Comp Mix",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sidechain: BoolParam::new("Sidechain", false),
+            detector_hpf: FloatParam::new(
+                "Detector HPF",
+                0.0,
+                FloatRange::Skewed { min: 0.0, max: 500.0, factor: 0.5 },
+            )
+            .with_step_size(1.0)
+            .with_value_to_string(v2s_hz_or_off())
+            .with_string_to_value(s2v_hz_or_off()),
+            sc_listen: BoolParam::new("SC Listen", false),
+            pre_clip: FloatParam::new(
+                "Pre Clip",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sat_coupling: FloatParam::new(
+                "GR Drive",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            duck_depth: FloatParam::new(
+                "Duck",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            duck_release: FloatParam::new(
+                "Duck Release",
+                200.0,
+                FloatRange::Skewed { min: 20.0, max: 1000.0, factor: 0.5 },
+            )
+            .with_step_size(1.0)
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
             clip: BoolParam::new("Clip at 0db", false),
+            clip_threshold: FloatParam::new(
+                "Clip Thresh",
+                0.0,
+                FloatRange::Linear { min: -6.0, max: 0.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            clip_knee: FloatParam::new(
+                "Clip Knee",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            wet_limit: BoolParam::new("Wet Limit", false),
+            wet_ceiling: FloatParam::new(
+                "Wet Ceiling",
+                0.0,
+                FloatRange::Linear { min: -12.0, max: 0.0 },
+            )
+            .with_step_size(0.01)
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            limiter_release: FloatParam::new(
+                "Clip Release",
+                0.0,
+                FloatRange::Skewed { min: 0.0, max: 500.0, factor: 0.5 },
+            )
+            .with_step_size(1.0)
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
             mix: FloatParam::new(
                 "Mix",
                 1.0,
                 FloatRange::Linear { min: 0.0, max: 1.0 },
             )
-            .with_step_size(0.00001),
+            .with_step_size(0.00001)
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
             gain: FloatParam::new(
                 "Gain",
                 0.0,
                 FloatRange::Linear { min: -12.0, max: 12.0 },
             )
-            .with_step_size(0.00001),
+            .with_step_size(0.00001)
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            bass_mono_freq: FloatParam::new(
+                "Bass Mono",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 300.0 },
+            )
+            .with_step_size(1.0)
+            .with_value_to_string(v2s_hz_or_off())
+            .with_string_to_value(s2v_hz_or_off()),
+            haas: IntParam::new(
+                "Haas",
+                0,
+                IntRange::Linear { min: 0, max: stereo::MAX_HAAS_SAMPLES as i32 },
+            )
+            .with_unit(" smp"),
             master_out: FloatParam::new(
                 "Master",
                 0.0,
                 FloatRange::Linear { min: -24.0, max: 24.0 },
             )
-            .with_step_size(0.00001),
+            .with_step_size(0.00001)
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            precision: EnumParam::new("Precision", Precision::Single),
+            meter_mode: EnumParam::new("Meters", MeterMode::PerBlock).non_automatable(),
+            rng_seed: Arc::new(AtomicU64::new(XorShiftRng::random_seed())),
+            peak_hold: Arc::new(AtomicBool::new(false)),
+            meter_reference: Arc::new(AtomicF32::new(0.0)),
+            peak_in: Arc::new(AtomicF32::new(0.0)),
+            peak_out: Arc::new(AtomicF32::new(0.0)),
+            peak_gr: Arc::new(AtomicF32::new(0.0)),
         }
     }
 }
@@ -166,11 +1182,21 @@ impl Plugin for UnderBrush {
         AudioIOLayout {
             main_input_channels: NonZeroU32::new(2),
             main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
             ..AudioIOLayout::const_default()
         },
         AudioIOLayout {
             main_input_channels: NonZeroU32::new(1),
             main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
             ..AudioIOLayout::const_default()
         },
     ];
@@ -188,6 +1214,13 @@ impl Plugin for UnderBrush {
         let params = self.params.clone();
         let in_meter = self.in_meter.clone();
         let out_meter = self.out_meter.clone();
+        let comp_threshold = self.comp_threshold.clone();
+        let gr_meter = self.gr_meter.clone();
+        let in_clip = self.in_clip.clone();
+        let in_crest = self.in_crest.clone();
+        let in_average = self.in_average.clone();
+        let correlation_meter = self.correlation_meter.clone();
+        let out_crest = self.out_crest.clone();
         create_egui_editor(
             self.params.editor_state.clone(),
             (),
@@ -221,40 +1254,187 @@ impl Plugin for UnderBrush {
                             .on_hover_text("by Ardura with nih-plug and egui");
 
                         // Peak Meters
+                        let meter_reference =
+                            params.meter_reference.load(std::sync::atomic::Ordering::Relaxed);
                         let in_meter =
                             util::gain_to_db(in_meter.load(std::sync::atomic::Ordering::Relaxed));
-                        let in_meter_text = if in_meter > util::MINUS_INFINITY_DB {
-                            format!("{in_meter:.1} dBFS Input")
-                        } else {
-                            String::from("-inf dBFS Input")
-                        };
-                        let in_meter_normalized = (in_meter + 60.0) / 60.0;
+                        let in_meter_text = meter_text(in_meter, meter_reference, "Input");
+                        let in_meter_normalized = meter_normalized(in_meter, meter_reference);
                         ui.allocate_space(egui::Vec2::splat(2.0));
-                        let in_meter_obj = DBMeter::new(in_meter_normalized).text(in_meter_text);
+                        let mut in_meter_obj = DBMeter::new(in_meter_normalized).text(in_meter_text);
+                        // Show where the compressor's adaptive threshold sits
+                        let comp_running = params.comp.value() || params.comp_analyze.value();
+                        if comp_running {
+                            let threshold_db = util::gain_to_db(
+                                comp_threshold.load(std::sync::atomic::Ordering::Relaxed),
+                            );
+                            if threshold_db > util::MINUS_INFINITY_DB {
+                                in_meter_obj = in_meter_obj.marker(meter_normalized(threshold_db, meter_reference));
+                            }
+                        }
                         ui.add(in_meter_obj);
 
                         let out_meter =
                             util::gain_to_db(out_meter.load(std::sync::atomic::Ordering::Relaxed));
-                        let out_meter_text = if out_meter > util::MINUS_INFINITY_DB {
-                            format!("{out_meter:.1} dBFS Output")
-                        } else {
-                            String::from("-inf dBFS Output")
-                        };
-                        let out_meter_normalized = (out_meter + 60.0) / 60.0;
+                        let out_meter_text = meter_text(out_meter, meter_reference, "Output");
+                        let out_meter_normalized = meter_normalized(out_meter, meter_reference);
                         ui.allocate_space(egui::Vec2::splat(2.0));
                         let out_meter_obj = DBMeter::new(out_meter_normalized).text(out_meter_text);
                         ui.add(out_meter_obj);
 
+                        // Gain reduction or upward boost, or what it would be while only analyzing
+                        if comp_running {
+                            let gr_db = gr_meter.load(std::sync::atomic::Ordering::Relaxed);
+                            let gr_label = match params.comp_direction.value() {
+                                CompDirection::Downward => "GR",
+                                CompDirection::Upward => "Boost",
+                            };
+                            let gr_text = if params.comp_analyze.value() {
+                                format!("{gr_db:.1} dB {gr_label} (analyze)")
+                            } else {
+                                format!("{gr_db:.1} dB {gr_label}")
+                            };
+                            ui.allocate_space(egui::Vec2::splat(2.0));
+                            ui.add(DBMeter::new(gr_db / GR_METER_RANGE_DB).text(gr_text));
+                        }
+
                         // Sliders
                         let monofont = FontId::monospace(12.0);
 
+                        // How much the dynamic range is being squeezed
+                        let in_crest_db = in_crest.load(std::sync::atomic::Ordering::Relaxed);
+                        let out_crest_db = out_crest.load(std::sync::atomic::Ordering::Relaxed);
+                        ui.label(RichText::new(format!("In: {in_crest_db:.0} dB → Out: {out_crest_db:.0} dB crest")).font(monofont.clone()))
+                            .on_hover_text("Crest factor, peak to RMS over 300 ms.
+Lower means less dynamic range");
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Trim ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.input_trim, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Input level into the whole chain");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Gate ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.noise_gate, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Turns the console input down 24 dB below this level
+so the saturation doesn't bring up hiss in the gaps");
+                        });
+
+                        // Gain staging hint: trim that puts the average input level at the
+                        // selected saturation type's sweet spot
+                        ui.horizontal(|ui|{
+                            let average_db = in_average.load(std::sync::atomic::Ordering::Relaxed);
+                            if average_db > TRIM_ANALYSIS_FLOOR_DB {
+                                let target_db = params.sat_type.value().sweet_spot_db();
+                                let suggested = (target_db - average_db).clamp(-24.0, 24.0);
+                                ui.label(RichText::new(format!("Hint {suggested:+.1} dB")).font(monofont.clone()))
+                                    .on_hover_text(format!(
+                                        "Average input {average_db:.1} dBFS over the last few seconds.
+This Type likes about {target_db:.0} dBFS average"
+                                    ));
+                                if ui.button(RichText::new("Apply").font(monofont.clone()))
+                                    .on_hover_text("Set the trim to the suggestion")
+                                    .clicked()
+                                {
+                                    setter.begin_set_parameter(&params.input_trim);
+                                    setter.set_parameter(&params.input_trim, suggested);
+                                    setter.end_set_parameter(&params.input_trim);
+                                }
+                            } else {
+                                ui.label(RichText::new("Hint: play some audio").font(monofont.clone()));
+                            }
+                        });
+
+                        // Utility section, applied before the console
+                        ui.label(RichText::new("Utility").font(monofont.clone()));
+                        ui.horizontal(|ui|{
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.swap_lr, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Swap the left and right channels");
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.mono_sum, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Sum left and right to mono");
+                        });
+                        ui.horizontal(|ui|{
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.invert_l, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Flip the polarity of the left channel");
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.invert_r, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Flip the polarity of the right channel");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Chans").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.channel_mask, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Process one side only, the other
+passes the input through untouched");
+                        });
+
+                        // Input clip LED, separate from the output meter going red
+                        let in_clipped = in_clip.load(std::sync::atomic::Ordering::Relaxed);
+                        let in_clip_text = RichText::new("Input Clip")
+                            .font(monofont.clone())
+                            .color(if in_clipped { Color32::RED } else { LIGHT_GREEN });
+                        if ui
+                            .add(egui::Label::new(in_clip_text).sense(egui::Sense::click()))
+                            .on_hover_text("Lights when the input goes over 0 dBFS
+before the saturation. Click to reset")
+                            .clicked()
+                        {
+                            in_clip.store(false, std::sync::atomic::Ordering::Relaxed);
+                        }
+
                         ui.horizontal(|ui|{
                             ui.label(RichText::new("Drive").font(monofont.clone()));
                             ui.add(
                                 widgets::ParamSlider::for_param(&params.drive, setter)
                                     .with_width(130.0),
                             )
-                            .on_hover_text("Signal overdrive to console");
+                            .on_hover_text("Signal overdrive to console,
+in dB of gain into the saturator, 0-20 dB
+or 20-40 dB with Extreme");
+                        });
+
+                        ui.vertical_centered(|ui|{
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.extreme, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Sound design: 10x drive (+20 dB) with
+the saturation held around 0 dBFS");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Punch").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.transient_enhance, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Boosts transients into the saturation
+for snappier drums with more bite");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Curve").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.drive_curve, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("How Drive maps to saturation intensity");
                         });
 
                         ui.horizontal(|ui|{
@@ -263,7 +1443,79 @@ impl Plugin for UnderBrush {
                                 widgets::ParamSlider::for_param(&params.sat_type, setter)
                                     .with_width(130.0),
                             )
-                            .on_hover_text("The style of saturation");
+                            .on_hover_text(format!(
+                                "The style of saturation\n{}",
+                                params.sat_type.value().description()
+                            ));
+                        });
+
+                        ui.vertical_centered(|ui|{
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.antialias, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Antiderivative anti-aliasing for Cubic and Quintic.
+Less aliasing without oversampling,
+rolls off the very top slightly");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Band ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.sat_band, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Saturate only the highs or lows,
+the other band passes clean");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Split").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.sat_band_freq, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Crossover frequency for Band (Hz)");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("2nd H").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.warmth_2nd, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Even harmonic warmth, the same amount
+at any level unlike the saturation");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("3rd H").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.warmth_3rd, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Odd harmonic warmth, the same amount
+at any level unlike the saturation");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Life ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.life, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Slowly wanders drive, crosstalk and
+linearizer frequency over seconds");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("XTalk").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.crosstalk_model, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Resistive: flat bleed
+Capacitive: bleed emphasizes highs
+Inductive: bleed emphasizes lows");
                         });
 
                         ui.horizontal(|ui|{
@@ -277,6 +1529,16 @@ A phase linearizer aligns
 sound frequencies in time");
                         });
 
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Couple").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.coupling, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("AC strips DC offset, DC passes it through
+(runaway offsets still get leaked away)");
+                        });
+
                         // Fix bypass switch being LOUD
                         if *&params.sat_type.value() == SaturationType::Bypass && *&params.drive.value() != 1.0 {
                             setter.begin_set_parameter(&params.drive);
@@ -298,15 +1560,128 @@ sound frequencies in time");
                                 BoolButton::BoolButton::for_param(&params.comp, setter, 5.0, 1.0, monofont.clone()),
                             )
                             .on_hover_text("Gentle auto compression");
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.comp_analyze, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Run the compressor for the GR meter only,
+the audio stays uncompressed");
                         });
 
                         ui.horizontal(|ui|{
-                            ui.label(RichText::new("Gain ").font(monofont.clone()));
+                            ui.label(RichText::new("PreClp").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.pre_clip, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("Soft clips spikes in the compressor's copy
+so it doesn't chase them");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Dir  ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.comp_direction, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Downward pulls peaks down,
+Upward raises quiet passages");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Makeup").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.makeup_mode, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("Fixed: original makeup
+Auto: adds back the average gain reduction,
+unity when compressing upward
+Manual: no makeup, use Gain");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Lag  ").font(monofont.clone()));
                             ui.add(
+                                widgets::ParamSlider::for_param(&params.makeup_lag, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("How fast the auto makeup tracks (ms)");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("CmpMix").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.comp_mix, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("Parallel compression: blends the compressed copy
+with the saturated signal, 1.0 is fully compressed");
+                        });
+
+                        // Sidechain: pick the key, filter it, listen to it while tuning
+                        ui.label(RichText::new("Detector").font(monofont.clone()));
+                        ui.horizontal(|ui|{
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.sidechain, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Key the compressor from the sidechain input
+instead of its own signal");
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.sc_listen, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Solo the exact signal feeding the detector,
+turn off to hear the compression again");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("SC HPF").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.detector_hpf, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("Keeps the lows from driving the
+gain reduction (Hz)");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Duck ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.duck_depth, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Rhythmic ducking depth. Pumps on every beat
+while the host plays, on transients otherwise
+(from the sidechain when it's enabled)");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("DuckRl").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.duck_release, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("How fast the signal recovers after each duck (ms)");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Color").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.sat_coupling, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Compressor gain reduction adds drive,
+so compressed passages get more color");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Gain ").font(monofont.clone()));
+                            let gain_slider = ui.add(
                                 widgets::ParamSlider::for_param(&params.gain, setter)
                                     .with_width(130.0),
                             )
-                            .on_hover_text("Output gain of signal");
+                            .on_hover_text("Output gain of signal
+Snaps to 0 dB, hold Alt to drag freely");
+                            snap_to_detents(ui, &gain_slider, &params.gain, setter, &[0.0]);
                         });
 
                         ui.vertical_centered(|ui|{
@@ -317,14 +1692,91 @@ sound frequencies in time");
                         });
 
                         ui.horizontal(|ui|{
-                            ui.label(RichText::new("Mix  ").font(monofont.clone()));
+                            ui.label(RichText::new("Thresh").font(monofont.clone()));
                             ui.add(
+                                widgets::ParamSlider::for_param(&params.clip_threshold, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("Level where the clipper starts catching peaks");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Knee ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.clip_knee, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Soft knee below the threshold.
+0 is a brick wall, higher is gentler");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Rel  ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.limiter_release, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("How fast the clipper lets go after a peak.
+0 clips each sample, longer turns peaks down smoothly");
+                        });
+
+                        ui.vertical_centered(|ui|{
+                            ui.add(
+                                BoolButton::BoolButton::for_param(&params.wet_limit, setter, 5.0, 1.0, monofont.clone()),
+                            )
+                            .on_hover_text("Soft limit the wet signal before the mix
+so heavy drive stays consistent at any Mix");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Ceil ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.wet_ceiling, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Ceiling of the wet path limiter");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Mix  ").font(monofont.clone()));
+                            let mix_slider = ui.add(
                                 widgets::ParamSlider::for_param(&params.mix, setter)
                                     .with_width(130.0),
                             )
-                            .on_hover_text("Wet/Dry of the processing effect");
+                            .on_hover_text("Wet/Dry of the processing effect
+Snaps to 0/50/100%, hold Alt to drag freely");
+                            snap_to_detents(ui, &mix_slider, &params.mix, setter, &[0.0, 0.5, 1.0]);
                         });
 
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("BassMo").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.bass_mono_freq, setter)
+                                    .with_width(120.0),
+                            )
+                            .on_hover_text("Sum L and R to mono below this frequency (Hz)");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Haas ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.haas, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Delays the right channel a few samples for width");
+                        });
+                        // Mono compatibility warning while the Haas delay is in
+                        if params.haas.value() > 0 {
+                            let correlation = correlation_meter.load(std::sync::atomic::Ordering::Relaxed);
+                            ui.label(
+                                RichText::new(format!("Correlation {correlation:+.2}"))
+                                    .font(monofont.clone())
+                                    .color(if correlation < 0.0 { Color32::RED } else { LIGHT_GREEN }),
+                            )
+                            .on_hover_text("+1 is mono, below 0 the sides cancel
+when summed to mono");
+                        }
+
                         ui.horizontal(|ui|{
                             ui.label(RichText::new("Master").font(monofont.clone()));
                             ui.add(
@@ -333,6 +1785,81 @@ sound frequencies in time");
                             )
                             .on_hover_text("Master volume of output");
                         });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Prec ").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.precision, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Filter precision. 64-bit reduces drift
+in the feedback filters for mastering.
+Offline renders always use 64-bit");
+                        });
+
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Meter").font(monofont.clone()));
+                            ui.add(
+                                widgets::ParamSlider::for_param(&params.meter_mode, setter)
+                                    .with_width(130.0),
+                            )
+                            .on_hover_text("Per block metering is lighter on CPU,
+per sample follows every sample");
+                        });
+
+                        // Meter calibration, where 0 VU sits in dBFS
+                        ui.horizontal(|ui|{
+                            ui.label(RichText::new("Ref  ").font(monofont.clone()));
+                            let mut reference = meter_reference;
+                            if ui.add(
+                                egui::DragValue::new(&mut reference)
+                                    .range(-24.0..=0.0)
+                                    .speed(0.1)
+                                    .fixed_decimals(1)
+                                    .suffix(" dBFS"),
+                            )
+                            .on_hover_text("Level that reads as 0 VU on the meters,
+0 dBFS shows plain dBFS")
+                            .changed()
+                            {
+                                params.meter_reference.store(reference, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            for preset in [-14.0, -18.0, -20.0] {
+                                if ui.selectable_label(meter_reference == preset, RichText::new(format!("{preset:.0}")).font(monofont.clone()))
+                                    .clicked()
+                                {
+                                    params.meter_reference.store(preset, std::sync::atomic::Ordering::Relaxed);
+                                }
+                            }
+                        });
+
+                        // Peak capture for noting down levels after a pass
+                        ui.horizontal(|ui|{
+                            let held = params.peak_hold.load(std::sync::atomic::Ordering::Relaxed);
+                            if ui.selectable_label(held, RichText::new("Hold").font(monofont.clone()))
+                                .on_hover_text("Freeze the captured peaks")
+                                .clicked()
+                            {
+                                params.peak_hold.store(!held, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            if ui.button(RichText::new("Clear").font(monofont.clone()))
+                                .on_hover_text("Reset the captured peaks")
+                                .clicked()
+                            {
+                                params.peak_in.store(0.0, std::sync::atomic::Ordering::Relaxed);
+                                params.peak_out.store(0.0, std::sync::atomic::Ordering::Relaxed);
+                                params.peak_gr.store(0.0, std::sync::atomic::Ordering::Relaxed);
+                            }
+                        });
+                        // Captured peaks read relative to the meter reference as well
+                        let peak_in_db = util::gain_to_db(params.peak_in.load(std::sync::atomic::Ordering::Relaxed)) - meter_reference;
+                        let peak_out_db = util::gain_to_db(params.peak_out.load(std::sync::atomic::Ordering::Relaxed)) - meter_reference;
+                        let peak_gr_db = params.peak_gr.load(std::sync::atomic::Ordering::Relaxed);
+                        let peak_gr_label = match params.comp_direction.value() {
+                            CompDirection::Downward => "GR",
+                            CompDirection::Upward => "Boost",
+                        };
+                        ui.label(RichText::new(format!("Max In {peak_in_db:.1} Out {peak_out_db:.1} {peak_gr_label} {peak_gr_db:.1}")).font(monofont.clone()));
                     });
                 });
             },
@@ -351,119 +1878,213 @@ sound frequencies in time");
             .powf((buffer_config.sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip())
             as f32;
 
+        // Bring everything to the host's rate before audio starts. The linearizer's delay line is
+        // resized on a rate change, which mustn't happen on the audio thread.
+        let sample_rate = buffer_config.sample_rate;
+        self.console.set_sample_rate(sample_rate);
+        self.compressor.set_sample_rate(sample_rate);
+        self.detector_hpf.set_sample_rate(sample_rate);
+        self.noise_gate.set_sample_rate(sample_rate);
+        self.warmth_left.set_sample_rate(sample_rate);
+        self.warmth_right.set_sample_rate(sample_rate);
+        self.bass_mono.set_sample_rate(sample_rate);
+        self.in_crest_meter.set_sample_rate(sample_rate);
+        self.out_crest_meter.set_sample_rate(sample_rate);
+        self.ducker.set_sample_rate(sample_rate);
+
+        // Hosts re-initialize when switching between realtime and offline processing
+        self.offline_render = buffer_config.process_mode == ProcessMode::Offline;
+
+        // Persisted state is restored by now, so this picks up the session's seed
+        self.rng
+            .set_seed(self.params.rng_seed.load(std::sync::atomic::Ordering::Relaxed));
+        self.drift.reset();
+
+        // Debug builds check the DSP against known signals at the real sample rate
+        #[cfg(debug_assertions)]
+        self_test::run(buffer_config.sample_rate);
+
         true
     }
 
+    fn reset(&mut self) {
+        // Restart the random sequences so renders come out the same every time
+        self.rng
+            .set_seed(self.params.rng_seed.load(std::sync::atomic::Ordering::Relaxed));
+        self.drift.reset();
+    }
+
     fn process(
         &mut self,
         buffer: &mut nih_plug::prelude::Buffer<'_>,
         _aux: &mut nih_plug::prelude::AuxiliaryBuffers<'_>,
         _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let slew: f32 = self.params.slew.value();
         let current_sample_rate = _context.transport().sample_rate;
-        let overallscale = current_sample_rate / 44100.0;
-        
-        self.console.set_sample_rate(current_sample_rate);
-        self.console.set_drive(self.params.drive.value());
-        self.console.set_saturation_type(self.params.sat_type.value());
-        self.console.set_crosstalk(0.03);
-        self.console.set_phase_linearizer_freq(self.params.l_hz.value());
-
-        self.compressor.set_sample_rate(current_sample_rate);
-
-        let mix = self.params.mix.value();
-
-        for mut channel_samples in buffer.iter_samples() {
-            // Get the length of our buffer to use later
-            let num_samples = channel_samples.len();
-            let localthreshold = slew / overallscale;
-
-            // Split left and right same way original subhoofer did
-            let mut out_l = *channel_samples.get_mut(0).unwrap();
-            let mut out_r = *channel_samples.get_mut(1).unwrap();
-            let dry_left = out_l;
-            let dry_right = out_r;
-
-            let mut in_amplitude: f32 = (out_l + out_r / 2.0).abs();
-
-            // Main Processing
-            (out_l, out_r) = self.console.process(out_l, out_r);
-
-            // Slew limiting
-            let mut clamp = out_l - self.prev_slew_l;
-            if clamp > localthreshold {
-                out_l = self.prev_slew_l + localthreshold;
-            }
-            if -clamp > localthreshold {
-                out_l = self.prev_slew_l - localthreshold;
-            }
-            self.prev_slew_l = out_l;
 
-            clamp = out_r - self.prev_slew_r;
-            if clamp > localthreshold {
-                out_r = self.prev_slew_r + localthreshold;
+        // Parameter reads at the block start are fine since sample accurate automation splits
+        // the block on every parameter change
+        let settings = self.block_settings(current_sample_rate);
+        self.start_block(&settings);
+
+        let sidechain = if self.params.sidechain.value() {
+            _aux.inputs.first().map(|buffer| buffer.as_slice_immutable())
+        } else {
+            None
+        };
+        let average_coeff = (-1.0 / (TRIM_ANALYSIS_SECONDS * current_sample_rate)).exp();
+
+        // Metering setup, per block metering only collects the block max in the loop
+        let meters_open = self.params.editor_state.is_open();
+        let meter_mode = self.params.meter_mode.value();
+        let mut block_meter_in: f32 = 0.0;
+        let mut block_meter_out: f32 = 0.0;
+
+        self.in_crest_meter.set_sample_rate(current_sample_rate);
+        self.out_crest_meter.set_sample_rate(current_sample_rate);
+
+        // Block statistics, these also give the peak capture its block peaks
+        self.stats_collector.start();
+
+        // Rhythmic ducking follows the beat grid while the host is playing
+        let transport = _context.transport();
+        let duck_sync = match (transport.playing, transport.pos_beats(), transport.tempo) {
+            (true, Some(start_beats), Some(tempo)) => {
+                Some((start_beats, tempo / 60.0 / current_sample_rate as f64))
             }
-            if -clamp > localthreshold {
-                out_r = self.prev_slew_r - localthreshold;
+            _ => None,
+        };
+
+        // Everything in `process_frame` is per sample so the output doesn't depend on the host's
+        // block size
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            // Mono runs the same chain with the one channel on both sides
+            let is_stereo = channel_samples.len() > 1;
+            let input_l = *channel_samples.get_mut(0).unwrap();
+            let input_r = if is_stereo { *channel_samples.get_mut(1).unwrap() } else { input_l };
+
+            // The trim hint measures the untrimmed input so its suggestion doesn't chase itself
+            if meters_open {
+                let mean_square = (input_l * input_l + input_r * input_r) * 0.5;
+                self.in_mean_square = mean_square + (self.in_mean_square - mean_square) * average_coeff;
             }
-            self.prev_slew_r = out_r;
 
-            if self.params.comp.value() {
-                out_l = self.compressor.process(out_l);
-                out_r = self.compressor.process(out_r);
+            let sidechain_key = match sidechain {
+                Some(channels) if !channels.is_empty() => {
+                    let key_l = channels[0][sample_idx];
+                    let key_r = channels.get(1).map_or(key_l, |channel| channel[sample_idx]);
+                    Some((key_l, key_r))
+                }
+                _ => None,
+            };
+            let beat_position = duck_sync
+                .map(|(start_beats, beats_per_sample)| start_beats + beats_per_sample * sample_idx as f64);
+
+            let ((dry_left, dry_right), (out_l, out_r)) = self.process_frame(
+                &settings,
+                (input_l, input_r),
+                is_stereo,
+                sidechain_key,
+                beat_position,
+            );
+
+            // Assign our output
+            *channel_samples.get_mut(0).unwrap() = out_l;
+            if is_stereo {
+                *channel_samples.get_mut(1).unwrap() = out_r;
             }
 
-            out_l = out_l * util::db_to_gain(self.params.gain.value());
-            out_r = out_r * util::db_to_gain(self.params.gain.value());
+            ///////////////////////////////////////////////////////////////////////////////
+
+            let in_amplitude: f32 = ((dry_left + dry_right) / 2.0).abs();
+            let out_amplitude = ((out_l + out_r) / 2.0).abs();
 
-            // Safety for our ears
-            if self.params.clip.value() {
-                out_l = out_l.clamp(-0.9999, 0.9999);
-                out_r = out_r.clamp(-0.9999, 0.9999);
+            // Input clip detection before any processing
+            if dry_left.abs() > 1.0 || dry_right.abs() > 1.0 {
+                self.in_clip.store(true, std::sync::atomic::Ordering::Relaxed);
             }
 
-            // Mix dry/wet
-            out_l = (1.0 - mix) * dry_left + mix * out_l;
-            out_r = (1.0 - mix) * dry_right + mix * out_r;
+            self.stats_collector.add_input(dry_left, dry_right);
+            self.stats_collector.add_output(out_l, out_r);
 
-            // Assign our output
-            *channel_samples.get_mut(0).unwrap() = out_l;
-            *channel_samples.get_mut(1).unwrap() = out_r;
+            // Only process the meters if the GUI is open
+            if meters_open {
+                if let Some(crest) = self.in_crest_meter.process(dry_left, dry_right) {
+                    self.in_crest.store(crest, std::sync::atomic::Ordering::Relaxed);
+                }
+                if let Some(crest) = self.out_crest_meter.process(out_l, out_r) {
+                    self.out_crest.store(crest, std::sync::atomic::Ordering::Relaxed);
+                }
+
+                match meter_mode {
+                    MeterMode::PerSample => {
+                        update_peak_meter(&self.in_meter, in_amplitude, self.out_meter_decay_weight);
+                        update_peak_meter(&self.out_meter, out_amplitude, self.out_meter_decay_weight);
+                    }
+                    MeterMode::PerBlock => {
+                        block_meter_in = block_meter_in.max(in_amplitude);
+                        block_meter_out = block_meter_out.max(out_amplitude);
+                    }
+                }
+            }
+        }
 
-            ///////////////////////////////////////////////////////////////////////////////
+        self.block_stats = self.stats_collector.finish();
+        let block_peak_in = self.block_stats.peak_in;
+        let block_peak_out = self.block_stats.peak_out;
+        // Upward compression meters its boost on the same scale
+        let block_peak_gr = self.block_stats.max_gain_reduction_db.max(self.block_stats.max_boost_db);
+
+        if meters_open {
+            self.in_average.store(
+                util::gain_to_db(self.in_mean_square.sqrt()),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+
+            // One meter update for the whole block, decaying by the block length
+            if meter_mode == MeterMode::PerBlock {
+                let block_decay_weight = self.out_meter_decay_weight.powi(buffer.samples() as i32);
+                update_peak_meter(&self.in_meter, block_meter_in, block_decay_weight);
+                update_peak_meter(&self.out_meter, block_meter_out, block_decay_weight);
+            }
 
-            let mut out_amplitude = out_l + out_r;
+            // Gain reduction meter, decaying like the level meters
+            let gr_decay_weight = self.out_meter_decay_weight.powi(buffer.samples() as i32);
+            update_peak_meter(&self.gr_meter, block_peak_gr, gr_decay_weight);
+
+            // Output correlation for the Haas readout, smoothed across blocks
+            if let Some(correlation) = self.correlation.take() {
+                let smoothed = self.correlation_meter.load(std::sync::atomic::Ordering::Relaxed);
+                self.correlation_meter.store(
+                    smoothed + (correlation - smoothed) * 0.2,
+                    std::sync::atomic::Ordering::Relaxed,
+                );
+            }
 
-            // Only process the meters if the GUI is open
-            if self.params.editor_state.is_open() {
-                // Input gain meter
-                in_amplitude = (in_amplitude / num_samples as f32).abs();
-                let current_in_meter: f32 =
-                    self.in_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_in_meter = if in_amplitude > current_in_meter {
-                    in_amplitude
-                } else {
-                    current_in_meter * self.out_meter_decay_weight
-                        + in_amplitude * (1.0 - self.out_meter_decay_weight)
-                };
-                self.in_meter
-                    .store(new_in_meter, std::sync::atomic::Ordering::Relaxed);
-
-                // Output gain meter
-                out_amplitude = (out_amplitude / num_samples as f32).abs();
-                let current_out_meter = self.out_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_out_meter = if out_amplitude > current_out_meter {
-                    out_amplitude
-                } else {
-                    current_out_meter * self.out_meter_decay_weight
-                        + out_amplitude * (1.0 - self.out_meter_decay_weight)
-                };
-                self.out_meter
-                    .store(new_out_meter, std::sync::atomic::Ordering::Relaxed);
+            // Compressor threshold marker
+            self.comp_threshold
+                .store(self.compressor.threshold, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        // Update the captured peaks unless they're being held
+        if !self.params.peak_hold.load(std::sync::atomic::Ordering::Relaxed) {
+            if block_peak_in > self.params.peak_in.load(std::sync::atomic::Ordering::Relaxed) {
+                self.params.peak_in.store(block_peak_in, std::sync::atomic::Ordering::Relaxed);
+            }
+            if block_peak_out > self.params.peak_out.load(std::sync::atomic::Ordering::Relaxed) {
+                self.params.peak_out.store(block_peak_out, std::sync::atomic::Ordering::Relaxed);
             }
+            if block_peak_gr > self.params.peak_gr.load(std::sync::atomic::Ordering::Relaxed) {
+                self.params.peak_gr.store(block_peak_gr, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        // Silent input with signal still coming out means the delay lines are flushing
+        if block_peak_in < SILENCE_THRESHOLD && block_peak_out >= SILENCE_THRESHOLD {
+            ProcessStatus::Tail((self.console.tail_samples() + self.haas.delay_samples()) as u32)
+        } else {
+            ProcessStatus::Normal
         }
-        ProcessStatus::Normal
     }
 }
 
@@ -488,3 +2109,111 @@ impl Vst3Plugin for UnderBrush {
 
 nih_export_clap!(UnderBrush);
 nih_export_vst3!(UnderBrush);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Settings that keep every stage of the chain busy
+    fn busy_settings(channel_mask: ChannelMask) -> BlockSettings {
+        BlockSettings {
+            input_trim: util::db_to_gain(3.0),
+            swap_lr: true,
+            invert_r: true,
+            noise_gate_db: -50.0,
+            drive: 4.0,
+            life: 1.0,
+            sat_type: SaturationType::Tube,
+            antialias: true,
+            transient_enhance: 0.5,
+            crosstalk_model: CrosstalkModel::Capacitive,
+            warmth_2nd: 0.5,
+            warmth_3rd: 0.5,
+            slew: 0.05,
+            comp: true,
+            makeup_mode: MakeupMode::Auto,
+            comp_mix: 0.7,
+            detector_hpf: 100.0,
+            pre_clip: 0.5,
+            sat_coupling: 0.5,
+            duck_depth: 0.5,
+            duck_release: 100.0,
+            gain: util::db_to_gain(2.0),
+            clip: true,
+            clip_threshold_db: -1.0,
+            clip_knee: 0.5,
+            clip_release: 100.0,
+            wet_limit: true,
+            wet_ceiling_db: -3.0,
+            mix: 0.8,
+            bass_mono_freq: 120.0,
+            haas: 12,
+            channel_mask,
+            ..UnderBrush::default().block_settings(SAMPLE_RATE)
+        }
+    }
+
+    /// Run the plugin's own chain over `input` in blocks of `block_size`
+    fn render(
+        settings: &BlockSettings,
+        input: &[(f32, f32)],
+        sidechain: Option<&[(f32, f32)]>,
+        block_size: usize,
+    ) -> Vec<(f32, f32)> {
+        let mut plugin = UnderBrush::default();
+        plugin.set_rng_seed(1234);
+        // The LFOs' first segment sits at the center, run them 20 seconds in so the drift is
+        // actually moving
+        for _ in 0..20 * SAMPLE_RATE as usize {
+            plugin.drift.tick(&mut plugin.rng, SAMPLE_RATE);
+        }
+
+        let mut output = Vec::with_capacity(input.len());
+        for (block_idx, block) in input.chunks(block_size).enumerate() {
+            plugin.start_block(settings);
+            for (offset, &frame) in block.iter().enumerate() {
+                let key = sidechain.map(|sidechain| sidechain[block_idx * block_size + offset]);
+                let (_, out) = plugin.process_frame(settings, frame, true, key, None);
+                output.push(out);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn output_is_independent_of_block_size() {
+        // A rising sweep with noise and quiet gaps, so the gate, compressor and clipper all move
+        let mut noise = XorShiftRng::new(7);
+        let input: Vec<(f32, f32)> = (0..2 * SAMPLE_RATE as usize)
+            .map(|n| {
+                let time = n as f32 / SAMPLE_RATE;
+                let level = if (n / 6000) % 3 == 2 { 0.001 } else { 0.8 };
+                let tone = level * (2.0 * std::f32::consts::PI * (60.0 + 400.0 * time) * time).sin();
+                (tone + 0.01 * noise.next_bipolar(), 0.7 * tone + 0.01 * noise.next_bipolar())
+            })
+            .collect();
+        // Short bursts every 250 ms for the ducker to trigger on
+        let sidechain: Vec<(f32, f32)> = (0..input.len())
+            .map(|n| {
+                let burst = if n % 12000 < 480 { 0.5 } else { 0.0 };
+                (burst * noise.next_bipolar(), burst * noise.next_bipolar())
+            })
+            .collect();
+
+        let cases = [
+            (busy_settings(ChannelMask::Both), None),
+            (busy_settings(ChannelMask::Left), Some(sidechain.as_slice())),
+        ];
+        for (settings, sidechain) in &cases {
+            // 1000 sample blocks also start off the 64 sample grid
+            let reference = render(settings, &input, *sidechain, 64);
+            for block_size in [512, 1000] {
+                let output = render(settings, &input, *sidechain, block_size);
+                let first_difference = reference.iter().zip(&output).position(|(a, b)| a != b);
+                assert_eq!(first_difference, None, "{block_size} sample blocks differ from 64");
+            }
+        }
+    }
+}
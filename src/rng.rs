@@ -0,0 +1,76 @@
+// rng.rs - Ardura 2025
+// Small seedable PRNG so anything random (drift, noise, dither) recalls identically per session
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// xorshift64* generator. Cheap, allocation free and deterministic for a given seed.
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.set_seed(seed);
+        rng
+    }
+
+    /// A fresh seed for a new plugin instance
+    pub fn random_seed() -> u64 {
+        RandomState::new().build_hasher().finish()
+    }
+
+    /// Restart the sequence from `seed`. The seed is scrambled (splitmix64) so nearby seeds give
+    /// unrelated sequences and the state is never zero.
+    pub fn set_seed(&mut self, seed: u64) {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        self.state = if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z };
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in `[-1, 1)`
+    pub fn next_bipolar(&mut self) -> f32 {
+        self.next_f32() * 2.0 - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(rng: &mut XorShiftRng) -> Vec<u64> {
+        (0..64).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let first = sequence(&mut XorShiftRng::new(1234));
+        assert_eq!(first, sequence(&mut XorShiftRng::new(1234)));
+        assert_ne!(first, sequence(&mut XorShiftRng::new(1235)));
+    }
+
+    #[test]
+    fn set_seed_restarts_the_sequence() {
+        let mut rng = XorShiftRng::new(1234);
+        let first = sequence(&mut rng);
+        rng.set_seed(1234);
+        assert_eq!(first, sequence(&mut rng));
+    }
+}
@@ -0,0 +1,106 @@
+// self_test.rs - Ardura 2025
+// Debug build sanity checks, run from initialize() at the host's sample rate
+
+use std::f32::consts::TAU;
+
+use nih_plug::prelude::*;
+
+use crate::analog_console::{AnalogConsoleProcessor, SaturationType, INTERNAL_CEILING};
+use crate::auto_compressor::{MakeupMode, SimpleAutoCompressor};
+
+/// Frequency of the test tone
+const TONE_HZ: f32 = 1000.0;
+
+/// Pass known signals through fresh DSP instances and check the results. Failures are logged
+/// through `nih_debug_assert!`, the plugin's own processors are left untouched.
+pub fn run(sample_rate: f32) -> bool {
+    let passed = dc_is_blocked(sample_rate) & saturation_is_bounded(sample_rate) & compressor_reduces(sample_rate);
+    nih_log!(
+        "Self-test at {sample_rate} Hz {}",
+        if passed { "passed" } else { "FAILED" }
+    );
+    passed
+}
+
+fn tone(sample_rate: f32, index: usize, amplitude: f32) -> f32 {
+    amplitude * (TAU * TONE_HZ * index as f32 / sample_rate).sin()
+}
+
+/// A second of DC through the AC coupled console should come out at (nearly) zero
+fn dc_is_blocked(sample_rate: f32) -> bool {
+    let mut console = AnalogConsoleProcessor::new(sample_rate);
+    console.set_saturation_type(SaturationType::Bypass);
+    console.set_drive(1.0);
+
+    let mut output = (0.0, 0.0);
+    for _ in 0..sample_rate as usize {
+        output = console.process(0.5, 0.5);
+    }
+
+    let residual = output.0.abs().max(output.1.abs());
+    let passed = residual < 0.001;
+    nih_debug_assert!(passed, "Self-test: DC blocker left {residual} of a 0.5 offset");
+    passed
+}
+
+/// Every saturation type at full drive on a +12 dBFS tone stays finite and within the internal
+/// headroom. The DC blocker can overshoot the bound on the way out, so allow twice the ceiling.
+fn saturation_is_bounded(sample_rate: f32) -> bool {
+    let mut passed = true;
+    for sat_type in [
+        SaturationType::Tape,
+        SaturationType::Tube,
+        SaturationType::Transistor,
+        SaturationType::LDR,
+        SaturationType::Cubic,
+        SaturationType::Quintic,
+        SaturationType::SoftClip,
+        SaturationType::Bypass,
+    ] {
+        let mut console = AnalogConsoleProcessor::new(sample_rate);
+        console.set_saturation_type(sat_type);
+        console.set_drive(10.0);
+
+        let mut peak: f32 = 0.0;
+        for index in 0..(sample_rate * 0.1) as usize {
+            let input = tone(sample_rate, index, 4.0);
+            let (left, right) = console.process(input, input);
+            peak = peak.max(left.abs()).max(right.abs());
+        }
+
+        let bounded = peak.is_finite() && peak <= INTERNAL_CEILING * 2.0;
+        nih_debug_assert!(
+            bounded,
+            "Self-test: {} saturation peaked at {peak}",
+            SaturationType::variants()[sat_type.to_index()]
+        );
+        passed &= bounded;
+    }
+    passed
+}
+
+/// Half a second of a 0 dBFS tone should get pulled down by the compressor
+fn compressor_reduces(sample_rate: f32) -> bool {
+    let mut compressor = SimpleAutoCompressor::new(sample_rate);
+    compressor.set_makeup_mode(MakeupMode::Manual);
+
+    let mut input_peak: f32 = 0.0;
+    let mut output_peak: f32 = 0.0;
+    for index in 0..(sample_rate * 0.5) as usize {
+        let input = tone(sample_rate, index, 1.0);
+        let output = compressor.process(input);
+        // Only look at the settled second half
+        if index as f32 > sample_rate * 0.25 {
+            input_peak = input_peak.max(input.abs());
+            output_peak = output_peak.max(output.abs());
+        }
+    }
+
+    let passed = compressor.gain_reduction_db > 0.5 && output_peak < input_peak;
+    nih_debug_assert!(
+        passed,
+        "Self-test: compressor reduced a 0 dBFS tone by {} dB",
+        compressor.gain_reduction_db
+    );
+    passed
+}
@@ -0,0 +1,86 @@
+// stats.rs - Ardura 2025
+// Per block processing statistics for metering, monitoring and debugging
+
+/// What happened during the last processed block. Levels are linear gain, taken over both
+/// channels. The input is measured after the trim and utility stage, which is also what the
+/// dry path gets, the output is what gets written to the buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlockStats {
+    /// Number of samples in the block
+    pub samples: usize,
+    pub peak_in: f32,
+    pub peak_out: f32,
+    pub rms_in: f32,
+    pub rms_out: f32,
+    /// Largest compressor gain reduction in dB, 0 when the compressor is off
+    pub max_gain_reduction_db: f32,
+    /// Largest upward compression boost in dB, 0 unless the compressor runs upward
+    pub max_boost_db: f32,
+    /// The output clipper changed at least one sample
+    pub clipper_engaged: bool,
+    /// The slew limiter held back at least one sample
+    pub slew_engaged: bool,
+}
+
+/// Fills in a `BlockStats` over the course of a block
+pub(crate) struct BlockStatsCollector {
+    stats: BlockStats,
+    sum_squares_in: f64,
+    sum_squares_out: f64,
+}
+
+impl Default for BlockStatsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockStatsCollector {
+    pub fn new() -> Self {
+        Self {
+            stats: BlockStats::default(),
+            sum_squares_in: 0.0,
+            sum_squares_out: 0.0,
+        }
+    }
+
+    /// Clear everything for a new block
+    pub fn start(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn add_input(&mut self, left: f32, right: f32) {
+        self.stats.samples += 1;
+        self.stats.peak_in = self.stats.peak_in.max(left.abs()).max(right.abs());
+        self.sum_squares_in += (left * left + right * right) as f64;
+    }
+
+    pub fn add_output(&mut self, left: f32, right: f32) {
+        self.stats.peak_out = self.stats.peak_out.max(left.abs()).max(right.abs());
+        self.sum_squares_out += (left * left + right * right) as f64;
+    }
+
+    /// Add the compressor's gain change, positive is reduction and negative is upward boost
+    pub fn add_gain_reduction(&mut self, gain_reduction_db: f32) {
+        self.stats.max_gain_reduction_db = self.stats.max_gain_reduction_db.max(gain_reduction_db);
+        self.stats.max_boost_db = self.stats.max_boost_db.max(-gain_reduction_db);
+    }
+
+    pub fn clipper_engaged(&mut self) {
+        self.stats.clipper_engaged = true;
+    }
+
+    pub fn slew_engaged(&mut self) {
+        self.stats.slew_engaged = true;
+    }
+
+    /// Statistics of everything added since `start`
+    pub fn finish(&self) -> BlockStats {
+        let channel_samples = (2 * self.stats.samples.max(1)) as f64;
+        BlockStats {
+            rms_in: (self.sum_squares_in / channel_samples).sqrt() as f32,
+            rms_out: (self.sum_squares_out / channel_samples).sqrt() as f32,
+            ..self.stats
+        }
+    }
+}
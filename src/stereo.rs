@@ -0,0 +1,207 @@
+// stereo.rs - Ardura 2025
+// Output stage stereo processors
+
+use std::f32::consts::PI;
+
+use nih_plug::prelude::Enum;
+
+/// Which output channels get the processed signal. The others pass the input through clean.
+#[derive(Clone, Copy, PartialEq, Enum)]
+pub enum ChannelMask {
+    #[name = "L + R"]
+    Both,
+    Left,
+    Right,
+}
+
+/// Sums the low end to mono below a crossover by removing the low part of the side signal.
+/// Since only the side gets filtered the highs stay fully stereo and L + R is untouched.
+pub struct BassMono {
+    sample_rate: f32,
+    freq_hz: f32,
+    side_lowpass: Biquad,
+}
+
+impl BassMono {
+    pub fn new(sample_rate: f32) -> Self {
+        let freq_hz = 120.0;
+        Self {
+            sample_rate,
+            freq_hz,
+            side_lowpass: Biquad::lowpass(sample_rate, freq_hz),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.side_lowpass.set_lowpass(self.sample_rate, self.freq_hz);
+        }
+    }
+
+    pub fn set_frequency(&mut self, freq_hz: f32) {
+        if freq_hz != self.freq_hz {
+            self.freq_hz = freq_hz;
+            self.side_lowpass.set_lowpass(self.sample_rate, self.freq_hz);
+        }
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let side = (left - right) * 0.5;
+        let side_low = self.side_lowpass.process(side);
+        (left - side_low, right + side_low)
+    }
+}
+
+/// Longest Haas delay in samples
+pub const MAX_HAAS_SAMPLES: usize = 30;
+
+/// Haas widener: delays the right channel by a few samples against the left. Cheap width, but
+/// it comb filters when summed to mono, hence the correlation readout next to it.
+pub struct HaasDelay {
+    buffer: [f32; MAX_HAAS_SAMPLES + 1],
+    write_pos: usize,
+    delay_samples: usize,
+}
+
+impl Default for HaasDelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HaasDelay {
+    pub fn new() -> Self {
+        Self {
+            buffer: [0.0; MAX_HAAS_SAMPLES + 1],
+            write_pos: 0,
+            delay_samples: 0,
+        }
+    }
+
+    pub fn set_delay(&mut self, delay_samples: usize) {
+        self.delay_samples = delay_samples.min(MAX_HAAS_SAMPLES);
+    }
+
+    pub fn delay_samples(&self) -> usize {
+        self.delay_samples
+    }
+
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let len = self.buffer.len();
+        self.buffer[self.write_pos] = right;
+        let delayed = self.buffer[(self.write_pos + len - self.delay_samples) % len];
+        self.write_pos = (self.write_pos + 1) % len;
+        (left, delayed)
+    }
+}
+
+/// Stereo correlation over a block: +1 is mono, 0 unrelated, negative cancels in mono
+pub struct Correlation {
+    sum_lr: f64,
+    sum_ll: f64,
+    sum_rr: f64,
+}
+
+impl Default for Correlation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Correlation {
+    pub fn new() -> Self {
+        Self {
+            sum_lr: 0.0,
+            sum_ll: 0.0,
+            sum_rr: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, left: f32, right: f32) {
+        self.sum_lr += (left * right) as f64;
+        self.sum_ll += (left * left) as f64;
+        self.sum_rr += (right * right) as f64;
+    }
+
+    /// Correlation of everything added since the last call, `None` if it was silent
+    pub fn take(&mut self) -> Option<f32> {
+        let energy = (self.sum_ll * self.sum_rr).sqrt();
+        let correlation = if energy > 0.0 {
+            Some((self.sum_lr / energy) as f32)
+        } else {
+            None
+        };
+        *self = Self::new();
+        correlation
+    }
+}
+
+/// Second order Butterworth low-pass or high-pass filter (RBJ cookbook)
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    pub fn lowpass(sample_rate: f32, freq_hz: f32) -> Self {
+        let mut biquad = Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        };
+        biquad.set_lowpass(sample_rate, freq_hz);
+        biquad
+    }
+
+    pub fn set_lowpass(&mut self, sample_rate: f32, freq_hz: f32) {
+        let freq_hz = freq_hz.clamp(10.0, sample_rate * 0.45);
+        let w0 = 2.0 * PI * freq_hz / sample_rate;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha;
+
+        self.b0 = (1.0 - cos_w0) * 0.5 / a0;
+        self.b1 = (1.0 - cos_w0) / a0;
+        self.b2 = self.b0;
+        self.a1 = -2.0 * cos_w0 / a0;
+        self.a2 = (1.0 - alpha) / a0;
+    }
+
+    pub fn highpass(sample_rate: f32, freq_hz: f32) -> Self {
+        let mut biquad = Self::lowpass(sample_rate, freq_hz);
+        biquad.set_highpass(sample_rate, freq_hz);
+        biquad
+    }
+
+    pub fn set_highpass(&mut self, sample_rate: f32, freq_hz: f32) {
+        let freq_hz = freq_hz.clamp(10.0, sample_rate * 0.45);
+        let w0 = 2.0 * PI * freq_hz / sample_rate;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha;
+
+        self.b0 = (1.0 + cos_w0) * 0.5 / a0;
+        self.b1 = -(1.0 + cos_w0) / a0;
+        self.b2 = self.b0;
+        self.a1 = -2.0 * cos_w0 / a0;
+        self.a2 = (1.0 - alpha) / a0;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        // Transposed direct form II
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
@@ -0,0 +1,79 @@
+// warmth.rs - Ardura 2025
+// Low order harmonic generator for warmth that doesn't depend on clipping
+
+use crate::analog_console::{DCBlocker, Precision};
+
+/// Keeps the normalization from blowing up on silence (about -80 dBFS)
+const ENVELOPE_FLOOR: f32 = 0.0001;
+/// Harmonic amount at full control travel. At 1.0 a sine picks up a 2nd harmonic 20 dB down,
+/// the 3rd lands 6 dB lower than that
+const MAX_AMOUNT: f32 = 0.2;
+
+/// Adds 2nd and 3rd harmonics from an `x²`/`x³` waveshaper. The signal is normalized by its
+/// peak envelope before shaping, so the harmonics stay the same distance below the fundamental
+/// at any level and quiet passages get colored as much as loud ones. Only the generated
+/// harmonics are DC blocked, the dry signal passes through as-is.
+pub struct HarmonicWarmth {
+    sample_rate: f32,
+    second: f32,
+    third: f32,
+    envelope: f32,
+    release_coeff: f32,
+    dc_blocker: DCBlocker,
+}
+
+impl HarmonicWarmth {
+    /// Envelope release, slow enough not to follow the waveform itself
+    const RELEASE_MS: f32 = 50.0;
+
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            second: 0.0,
+            third: 0.0,
+            envelope: 0.0,
+            release_coeff: Self::calculate_release(sample_rate),
+            dc_blocker: DCBlocker::new(0.995),
+        }
+    }
+
+    fn calculate_release(sample_rate: f32) -> f32 {
+        (-1.0 / (Self::RELEASE_MS * 0.001 * sample_rate)).exp()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.release_coeff = Self::calculate_release(sample_rate);
+        }
+    }
+
+    /// Precision of the harmonics' DC blocker, follows the console's
+    pub fn set_precision(&mut self, precision: Precision) {
+        self.dc_blocker.set_precision(precision);
+    }
+
+    /// Set the 2nd and 3rd harmonic amounts, 0-1
+    pub fn set_amounts(&mut self, second: f32, third: f32) {
+        self.second = second.clamp(0.0, 1.0) * MAX_AMOUNT;
+        self.third = third.clamp(0.0, 1.0) * MAX_AMOUNT;
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        // Peak envelope: instant attack keeps the normalized signal within -1..1
+        let level = input.abs();
+        self.envelope = if level > self.envelope {
+            level
+        } else {
+            level + (self.envelope - level) * self.release_coeff
+        };
+
+        let envelope = self.envelope.max(ENVELOPE_FLOOR);
+        let normalized = input / envelope;
+        let harmonics = envelope
+            * (self.second * normalized * normalized + self.third * normalized * normalized * normalized);
+
+        // x² is all positive, strip its offset before mixing it in
+        input + self.dc_blocker.process(harmonics)
+    }
+}